@@ -0,0 +1,109 @@
+//! Measures the `/proc` reader on a synthetic table of 5,000 processes
+//!
+//! The table is a fake procfs tree generated in a temporary directory and read
+//! through `ProcfsTable::with_root`, so no process is spawned. sysinfo cannot
+//! read a fake procfs; it is measured on the live table instead, and the two
+//! compare per process. Run with `cargo bench --bench process_table`; set
+//! `WICK_BENCH_PROCESSES` to change the table size.
+
+#[cfg(target_os = "linux")]
+fn main() {
+    use process_wick::{build_process_tree_with, ProcessTable, ProcfsTable, SysinfoTable};
+    use std::path::PathBuf;
+    use std::time::{Duration, Instant};
+
+    const ROUNDS: u32 = 10;
+
+    /// Removes the fake procfs tree, even if the benchmark panics
+    struct FakeProcfs(PathBuf);
+
+    impl FakeProcfs {
+        /// Writes `count` processes: PID 1 is the root, and every other PID
+        /// hangs below PID / 8 (or PID 1), giving a wide tree a few levels deep
+        fn generate(count: u32) -> std::io::Result<Self> {
+            let root = std::env::temp_dir().join(format!("wick-bench-{}", std::process::id()));
+            let fake = FakeProcfs(root);
+            std::fs::create_dir_all(&fake.0)?;
+            std::fs::write(fake.0.join("stat"), "cpu 0 0 0 0\nbtime 1700000000\n")?;
+            for pid in 1..=count {
+                let dir = fake.0.join(pid.to_string());
+                std::fs::create_dir(&dir)?;
+                let stat = format!(
+                    "{} (sleep) S {} {} {} 0 -1 4194304 0 0 0 0 0 0 0 0 20 0 1 0 {} 0 0",
+                    pid,
+                    if pid == 1 { 0 } else { (pid / 8).max(1) },
+                    pid,
+                    1,
+                    u64::from(pid) * 10
+                );
+                std::fs::write(dir.join("stat"), stat)?;
+            }
+            Ok(fake)
+        }
+    }
+
+    impl Drop for FakeProcfs {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn measure(label: &str, processes: usize, mut run: impl FnMut() -> usize) -> Duration {
+        // Warm up the page cache and sysinfo's lazy statics
+        run();
+        let start = Instant::now();
+        let mut seen = 0;
+        for _ in 0..ROUNDS {
+            seen = run();
+        }
+        let per_round = start.elapsed() / ROUNDS;
+        let per_process = per_round / processes.max(1) as u32;
+        println!(
+            "{:<28} {:>10.2?} per round, {:>6.2?} per process ({} seen)",
+            label, per_round, per_process, seen
+        );
+        per_process
+    }
+
+    let wanted: u32 = std::env::var("WICK_BENCH_PROCESSES")
+        .ok()
+        .and_then(|n| n.parse().ok())
+        .unwrap_or(5_000);
+    let fake = FakeProcfs::generate(wanted).expect("Failed to write the fake procfs");
+    let procfs = ProcfsTable::with_root(&fake.0);
+    let total = procfs.processes().len();
+    let live = SysinfoTable.processes().len();
+    println!(
+        "Synthetic table: {} processes, live table: {} processes\n",
+        total, live
+    );
+
+    let root = std::process::id();
+    let sysinfo_list = measure("sysinfo (live): list", live, || {
+        SysinfoTable.processes().len()
+    });
+    let procfs_list = measure("procfs: list", total, || procfs.processes().len());
+    let sysinfo_tree = measure("sysinfo (live): build tree", live, || {
+        build_process_tree_with(&SysinfoTable, 1).len()
+    });
+    let procfs_tree = measure("procfs: build tree", total, || {
+        build_process_tree_with(&procfs, 1).len()
+    });
+    let sysinfo_alive = measure("sysinfo (live): is_alive", 1, || {
+        usize::from(SysinfoTable.is_alive(root))
+    });
+    let procfs_alive = measure("procfs: is_alive", 1, || usize::from(procfs.is_alive(1)));
+
+    let speedup = |slow: Duration, fast: Duration| slow.as_secs_f64() / fast.as_secs_f64();
+    println!(
+        "\nPer process, procfs is {:.1}x faster listing, {:.1}x building trees, {:.1}x checking liveness",
+        speedup(sysinfo_list, procfs_list),
+        speedup(sysinfo_tree, procfs_tree),
+        speedup(sysinfo_alive, procfs_alive)
+    );
+}
+
+#[cfg(not(target_os = "linux"))]
+fn main() {
+    println!("The /proc reader is Linux-only; nothing to compare.");
+}
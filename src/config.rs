@@ -0,0 +1,1050 @@
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::graceful::GracefulStop;
+use crate::hang::HangDetection;
+use crate::hooks::{Hook, Hooks, DEFAULT_HOOK_TIMEOUT};
+use crate::limits::{ByteSize, CpuLimit, ResourceLimits};
+use crate::process_table::ProcessTable;
+use crate::reaper::KillSignal;
+use crate::safety::ProtectRule;
+use crate::vengeance::{customize_ladder, default_ladder, shutdown_waves, Rung};
+use crate::{get_dog_pid, parse_duration, TargetSpec};
+
+/// Default time in seconds between SIGTERM and SIGKILL
+pub const DEFAULT_VENGEANCE_DELAY: u64 = 5;
+
+/// Default time in seconds between each check on the dog
+pub const DEFAULT_TICK: u64 = 3;
+
+/// A `wick.toml` file declaring one or more contracts
+///
+/// ```toml
+/// protect = ["name:sshd", "uid:0"]
+///
+/// [logging]
+/// level = "debug"
+///
+/// [[contract]]
+/// id = "web"
+/// dog = { pidfile = "/run/web.pid" }
+/// max_rss = "2G"
+/// targets = [1234, { name = "worker", delay = 30, signal = "INT", max_cpu = "90%/60s" }, { pidfile = "/run/redis.pid", stop = "redis-cli shutdown" }]
+/// ladder = [{ signal = "INT", wait = 10 }, { signal = "TERM", wait = 5 }, { signal = "KILL" }]
+///
+/// [contract.hooks]
+/// before_kill = { command = "redis-cli save", timeout = 60 }
+/// after_kill = "rm -f /run/web.sock"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    /// Processes no contract may ever signal, e.g. `["name:sshd", "uid:0"]`
+    #[serde(default)]
+    pub protect: Vec<ProtectRule>,
+    #[serde(default, rename = "contract")]
+    pub contracts: Vec<ContractConfig>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LoggingConfig {
+    pub level: Option<String>,
+    pub file: Option<String>,
+}
+
+/// A single contract as declared in the configuration file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ContractConfig {
+    /// Name used in logs and on the control socket; defaults to `c1`, `c2`, ...
+    pub id: Option<String>,
+    /// Process to watch; defaults to the parent of process-wick
+    pub dog: Option<Selector>,
+    pub targets: Vec<TargetConfig>,
+    /// Seconds between SIGTERM and SIGKILL, when no ladder is given
+    pub vengeance_delay: Option<u64>,
+    pub tick: Option<u64>,
+    /// Signals sent in order, each followed by its wait in seconds
+    pub ladder: Option<Vec<RungConfig>>,
+    /// Memory budget for each target's tree, e.g. `"2G"`
+    pub max_rss: Option<ByteSize>,
+    /// CPU budget for each target's tree, e.g. `"90%/60s"`
+    pub max_cpu: Option<CpuLimit>,
+    /// Executes vengeance this long after the contract starts, e.g. `"30m"`,
+    /// even if the dog is still alive
+    #[serde(default, deserialize_with = "deserialize_duration")]
+    pub max_lifetime: Option<Duration>,
+    /// Executes vengeance at this RFC 3339 time, even if the dog is still alive
+    #[serde(default, deserialize_with = "deserialize_deadline")]
+    pub deadline: Option<DateTime<FixedOffset>>,
+    /// Counts the dog as dead once it looks hung, e.g. `{ stuck_ticks = 5 }`
+    pub hang: Option<HangDetection>,
+    /// Also takes down whatever shares a target's own session or process group;
+    /// that is every non-leader process of the session, unrelated jobs included
+    #[serde(default)]
+    pub sweep_session: bool,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+}
+
+/// Hook commands of a contract; `timeout` applies to hooks without their own
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HooksConfig {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub on_dog_death: Option<HookConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_kill: Option<HookConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_kill: Option<HookConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+/// A hook written either as a bare command or as `{ command, timeout }`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "HookDef")]
+pub struct HookConfig {
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum HookDef {
+    Command(String),
+    Table(HookTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct HookTable {
+    command: String,
+    timeout: Option<u64>,
+}
+
+impl From<HookDef> for HookConfig {
+    fn from(def: HookDef) -> Self {
+        match def {
+            HookDef::Command(command) => HookConfig {
+                command,
+                timeout: None,
+            },
+            HookDef::Table(table) => HookConfig {
+                command: table.command,
+                timeout: table.timeout,
+            },
+        }
+    }
+}
+
+fn deserialize_duration<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<Duration>, D::Error> {
+    let duration = String::deserialize(deserializer)?;
+    parse_duration(&duration)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+fn deserialize_deadline<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+    let deadline = String::deserialize(deserializer)?;
+    parse_deadline(&deadline)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Parses an RFC 3339 time such as `2025-06-01T18:00:00Z`
+pub fn parse_deadline(deadline: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(deadline.trim())
+        .map_err(|e| format!("Invalid deadline {}: {}", deadline.trim(), e))
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn hooks(&self) -> Hooks {
+        let hook = |config: &Option<HookConfig>| {
+            config.as_ref().map(|config| {
+                let timeout = config
+                    .timeout
+                    .or(self.timeout)
+                    .unwrap_or(DEFAULT_HOOK_TIMEOUT);
+                Hook::new(config.command.clone(), Duration::from_secs(timeout))
+            })
+        };
+        Hooks {
+            on_dog_death: hook(&self.on_dog_death),
+            before_kill: hook(&self.before_kill),
+            after_kill: hook(&self.after_kill),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RungConfig {
+    pub signal: KillSignal,
+    #[serde(default)]
+    pub wait: u64,
+}
+
+/// How a process is picked out in the configuration file
+///
+/// Written either as a bare PID or as a table with exactly one of `pid`,
+/// `parent = true`, `pidfile` or `name`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "SelectorDef")]
+pub enum Selector {
+    Pid(u32),
+    /// The parent of process-wick itself
+    Parent,
+    /// A file containing a PID, read when the contract starts
+    Pidfile(PathBuf),
+    /// Every process with this executable name
+    Name(String),
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SelectorDef {
+    Pid(u32),
+    Table(SelectorTable),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SelectorTable {
+    pid: Option<u32>,
+    #[serde(default)]
+    parent: bool,
+    pidfile: Option<PathBuf>,
+    name: Option<String>,
+}
+
+impl TryFrom<SelectorDef> for Selector {
+    type Error = String;
+
+    fn try_from(def: SelectorDef) -> Result<Self, Self::Error> {
+        match def {
+            SelectorDef::Pid(pid) => Ok(Selector::Pid(pid)),
+            SelectorDef::Table(table) => table.into_selector(),
+        }
+    }
+}
+
+impl SelectorTable {
+    fn into_selector(self) -> Result<Selector, String> {
+        let mut selectors = Vec::new();
+        if let Some(pid) = self.pid {
+            selectors.push(Selector::Pid(pid));
+        }
+        if self.parent {
+            selectors.push(Selector::Parent);
+        }
+        if let Some(path) = self.pidfile {
+            selectors.push(Selector::Pidfile(path));
+        }
+        if let Some(name) = self.name {
+            selectors.push(Selector::Name(name));
+        }
+
+        match selectors.len() {
+            1 => Ok(selectors.remove(0)),
+            0 => Err("Selector needs one of pid, parent, pidfile or name".to_string()),
+            _ => Err("Selector must use only one of pid, parent, pidfile or name".to_string()),
+        }
+    }
+}
+
+/// A target selector with optional per-target settings
+///
+/// Written as a selector, with extra keys in the table form:
+/// `{ name = "redis-server", id = "db", after = ["api"], stop = "redis-cli shutdown" }`.
+/// The settings apply to every process the selector matches.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "TargetDef")]
+pub struct TargetConfig {
+    pub selector: Selector,
+    /// Name other targets use to refer to this one in `after`/`before`
+    pub id: Option<String>,
+    pub stop: Option<GracefulStop>,
+    /// Seconds to wait after the first signal, instead of the contract's
+    pub delay: Option<u64>,
+    /// First signal to send, instead of the contract's
+    pub signal: Option<KillSignal>,
+    /// Targets that must be dead before this one is signalled
+    pub after: Vec<TargetRef>,
+    /// Targets that may only be signalled once this one is dead
+    pub before: Vec<TargetRef>,
+    /// Budgets for this target's tree, instead of the contract's
+    pub limits: ResourceLimits,
+}
+
+/// Reference to another target of the same contract: its `id`, or the PID of a `pid` target
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum TargetRef {
+    Pid(u32),
+    Id(String),
+}
+
+impl fmt::Display for TargetRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TargetRef::Pid(pid) => write!(f, "{}", pid),
+            TargetRef::Id(id) => write!(f, "{}", id),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TargetDef {
+    Pid(u32),
+    Table(Box<TargetTable>),
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TargetTable {
+    pid: Option<u32>,
+    #[serde(default)]
+    parent: bool,
+    pidfile: Option<PathBuf>,
+    name: Option<String>,
+    id: Option<String>,
+    stop: Option<GracefulStop>,
+    delay: Option<u64>,
+    signal: Option<KillSignal>,
+    #[serde(default)]
+    after: Vec<TargetRef>,
+    #[serde(default)]
+    before: Vec<TargetRef>,
+    max_rss: Option<ByteSize>,
+    max_cpu: Option<CpuLimit>,
+}
+
+impl TryFrom<TargetDef> for TargetConfig {
+    type Error = String;
+
+    fn try_from(def: TargetDef) -> Result<Self, Self::Error> {
+        match def {
+            TargetDef::Pid(pid) => Ok(Selector::Pid(pid).into()),
+            TargetDef::Table(table) => {
+                let selector = SelectorTable {
+                    pid: table.pid,
+                    parent: table.parent,
+                    pidfile: table.pidfile,
+                    name: table.name,
+                }
+                .into_selector()?;
+                Ok(TargetConfig {
+                    selector,
+                    id: table.id,
+                    stop: table.stop,
+                    delay: table.delay,
+                    signal: table.signal,
+                    after: table.after,
+                    before: table.before,
+                    limits: ResourceLimits {
+                        max_rss: table.max_rss,
+                        max_cpu: table.max_cpu,
+                    },
+                })
+            }
+        }
+    }
+}
+
+impl From<Selector> for TargetConfig {
+    fn from(selector: Selector) -> Self {
+        Self {
+            selector,
+            id: None,
+            stop: None,
+            delay: None,
+            signal: None,
+            after: Vec::new(),
+            before: Vec::new(),
+            limits: ResourceLimits::default(),
+        }
+    }
+}
+
+impl From<TargetSpec> for TargetConfig {
+    fn from(spec: TargetSpec) -> Self {
+        Self {
+            delay: spec.delay,
+            signal: spec.signal,
+            after: spec.after.into_iter().map(TargetRef::Pid).collect(),
+            before: spec.before.into_iter().map(TargetRef::Pid).collect(),
+            limits: spec.limits,
+            ..Selector::Pid(spec.pid).into()
+        }
+    }
+}
+
+impl TargetConfig {
+    /// Whether the target sets anything beyond its selector
+    pub fn has_settings(&self) -> bool {
+        !self.same_settings(&self.selector.clone().into())
+    }
+
+    /// Whether `other` treats its processes the same way, whatever its selector
+    pub fn same_settings(&self, other: &TargetConfig) -> bool {
+        let mut this = self.clone();
+        this.selector = other.selector.clone();
+        this == *other
+    }
+
+    /// This target's own ladder, if it overrides the contract's first rung
+    pub fn ladder(&self, contract_ladder: &[Rung]) -> Option<Vec<Rung>> {
+        if self.delay.is_none() && self.signal.is_none() {
+            return None;
+        }
+        Some(customize_ladder(
+            contract_ladder,
+            self.signal,
+            self.delay.map(Duration::from_secs),
+        ))
+    }
+}
+
+impl fmt::Display for TargetConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(id) = &self.id {
+            write!(f, "{}=", id)?;
+        }
+        write!(f, "{}", self.selector)?;
+        if let Some(signal) = self.signal {
+            write!(f, " signal={}", signal)?;
+        }
+        if let Some(delay) = self.delay {
+            write!(f, " delay={}s", delay)?;
+        }
+        for other in &self.after {
+            write!(f, " after={}", other)?;
+        }
+        for other in &self.before {
+            write!(f, " before={}", other)?;
+        }
+        if !self.limits.is_empty() {
+            write!(f, " (limits: {})", self.limits)?;
+        }
+        if let Some(stop) = &self.stop {
+            write!(f, " (stop: {})", stop)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Selector::Pid(pid) => write!(f, "pid {}", pid),
+            Selector::Parent => write!(f, "parent"),
+            Selector::Pidfile(path) => write!(f, "pidfile {}", path.display()),
+            Selector::Name(name) => write!(f, "name {}", name),
+        }
+    }
+}
+
+impl Selector {
+    /// Finds the PIDs this selector currently refers to
+    pub fn resolve(&self, table: &dyn ProcessTable) -> Result<Vec<u32>, String> {
+        match self {
+            Selector::Pid(pid) => Ok(vec![*pid]),
+            Selector::Parent => Ok(vec![get_dog_pid(None)]),
+            Selector::Pidfile(path) => {
+                let contents = std::fs::read_to_string(path)
+                    .map_err(|e| format!("Failed to read pidfile {}: {}", path.display(), e))?;
+                let pid = contents
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid PID in pidfile {}", path.display()))?;
+                Ok(vec![pid])
+            }
+            Selector::Name(name) => {
+                let mut pids: Vec<u32> = table
+                    .processes()
+                    .into_iter()
+                    .filter(|p| &p.name == name)
+                    .map(|p| p.pid)
+                    .collect();
+                if pids.is_empty() {
+                    return Err(format!("No process named {}", name));
+                }
+                pids.sort_unstable();
+                Ok(pids)
+            }
+        }
+    }
+}
+
+impl ContractConfig {
+    pub fn vengeance_delay(&self) -> Duration {
+        Duration::from_secs(self.vengeance_delay.unwrap_or(DEFAULT_VENGEANCE_DELAY))
+    }
+
+    pub fn tick(&self) -> Duration {
+        Duration::from_secs(self.tick.unwrap_or(DEFAULT_TICK))
+    }
+
+    /// The configured ladder, or SIGTERM → delay → SIGKILL
+    pub fn ladder(&self) -> Vec<Rung> {
+        match &self.ladder {
+            Some(rungs) => rungs
+                .iter()
+                .map(|rung| Rung::new(rung.signal, Duration::from_secs(rung.wait)))
+                .collect(),
+            None => default_ladder(self.vengeance_delay()),
+        }
+    }
+
+    /// Rewrites every literal PID: the dog, `pid` targets and PID references
+    /// in `after`/`before`
+    pub fn map_pids(
+        &mut self,
+        mut map: impl FnMut(u32) -> Result<u32, String>,
+    ) -> Result<(), String> {
+        if let Some(Selector::Pid(pid)) = &mut self.dog {
+            *pid = map(*pid)?;
+        }
+        for target in &mut self.targets {
+            if let Selector::Pid(pid) = &mut target.selector {
+                *pid = map(*pid)?;
+            }
+            for other in target.after.iter_mut().chain(target.before.iter_mut()) {
+                if let TargetRef::Pid(pid) = other {
+                    *pid = map(*pid)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Budgets applying to every target without its own
+    pub fn limits(&self) -> ResourceLimits {
+        ResourceLimits {
+            max_rss: self.max_rss,
+            max_cpu: self.max_cpu,
+        }
+    }
+
+    /// Resolves the dog, which must match exactly one process
+    pub fn resolve_dog(&self, table: &dyn ProcessTable) -> Result<u32, String> {
+        let selector = self.dog.as_ref().unwrap_or(&Selector::Parent);
+        match selector.resolve(table)?.as_slice() {
+            [0] => Err(format!(
+                "Dog selector {} resolves to PID 0, which is not a process",
+                selector
+            )),
+            [pid] => Ok(*pid),
+            pids => Err(format!(
+                "Dog selector {} matches {} processes: {:?}",
+                selector,
+                pids.len(),
+                pids
+            )),
+        }
+    }
+
+    /// Dependencies between targets, as "target index stops after these indices"
+    ///
+    /// Fails on references to unknown targets and on cycles.
+    pub fn target_dependencies(&self) -> Result<HashMap<u32, BTreeSet<u32>>, String> {
+        let find = |other: &TargetRef| {
+            self.targets
+                .iter()
+                .position(|target| match other {
+                    TargetRef::Id(id) => target.id.as_ref() == Some(id),
+                    TargetRef::Pid(pid) => target.selector == Selector::Pid(*pid),
+                })
+                .map(|index| index as u32)
+                .ok_or_else(|| format!("Unknown target {}", other))
+        };
+
+        let mut after: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+        for (index, target) in self.targets.iter().enumerate() {
+            let index = index as u32;
+            for other in &target.after {
+                after.entry(index).or_default().insert(find(other)?);
+            }
+            for other in &target.before {
+                after.entry(find(other)?).or_default().insert(index);
+            }
+        }
+
+        let indices: Vec<u32> = (0..self.targets.len() as u32).collect();
+        shutdown_waves(&indices, &after)
+            .map_err(|_| "Dependency cycle between targets".to_string())?;
+        Ok(after)
+    }
+
+    /// Translates target dependencies into PIDs once the targets are resolved
+    pub fn resolve_dependencies(
+        &self,
+        resolved: &BTreeMap<u32, &TargetConfig>,
+    ) -> Result<HashMap<u32, BTreeSet<u32>>, String> {
+        let pids_of = |index: u32| {
+            let target = &self.targets[index as usize];
+            resolved
+                .iter()
+                .filter(move |(_, resolved)| std::ptr::eq(**resolved, target))
+                .map(|(&pid, _)| pid)
+        };
+
+        let mut after: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+        for (index, deps) in self.target_dependencies()? {
+            for pid in pids_of(index) {
+                for &dep in &deps {
+                    after.entry(pid).or_default().extend(pids_of(dep));
+                }
+            }
+        }
+        Ok(after)
+    }
+
+    /// Settings that differ from `other`, by config key, leaving out `id`
+    ///
+    /// Targets count as changed only if `other` has a target with settings
+    /// beyond its selector that this contract lacks; a change of selectors
+    /// alone is not listed.
+    pub fn changed_keys(&self, other: &ContractConfig) -> Vec<&'static str> {
+        let mut keys = Vec::new();
+        let mut differs = |key, changed| {
+            if changed {
+                keys.push(key);
+            }
+        };
+        differs("dog", self.dog != other.dog);
+        differs(
+            "targets",
+            other
+                .targets
+                .iter()
+                .any(|target| target.has_settings() && !self.targets.contains(target)),
+        );
+        differs(
+            "vengeance_delay",
+            self.vengeance_delay != other.vengeance_delay,
+        );
+        differs("tick", self.tick != other.tick);
+        differs("ladder", self.ladder != other.ladder);
+        differs("max_rss", self.max_rss != other.max_rss);
+        differs("max_cpu", self.max_cpu != other.max_cpu);
+        differs("max_lifetime", self.max_lifetime != other.max_lifetime);
+        differs("deadline", self.deadline != other.deadline);
+        differs("hang", self.hang != other.hang);
+        differs("sweep_session", self.sweep_session != other.sweep_session);
+        differs("hooks", self.hooks != other.hooks);
+        keys
+    }
+
+    /// Resolves every target selector, keyed by PID
+    ///
+    /// A PID matched by several selectors keeps the last one.
+    pub fn resolve_targets<'a>(
+        &'a self,
+        table: &dyn ProcessTable,
+    ) -> Result<BTreeMap<u32, &'a TargetConfig>, String> {
+        let mut targets = BTreeMap::new();
+        for target in &self.targets {
+            for pid in target.selector.resolve(table)? {
+                targets.insert(pid, target);
+            }
+        }
+        Ok(targets)
+    }
+}
+
+impl Config {
+    /// Reads and validates a configuration file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&contents).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Parses and validates configuration from a TOML string
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut config: Config = toml::from_str(contents).map_err(|e| e.to_string())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Keys of `reloaded` that differ from this configuration and only take
+    /// effect on restart, e.g. `logging`, `c1.tick` or `contract c2 (new)`
+    ///
+    /// Target selectors are left out, since those can be reloaded.
+    pub fn restart_keys(&self, reloaded: &Config) -> Vec<String> {
+        let mut keys = Vec::new();
+        if self.logging != reloaded.logging {
+            keys.push("logging".to_string());
+        }
+        if self.protect != reloaded.protect {
+            keys.push("protect".to_string());
+        }
+        for contract in &reloaded.contracts {
+            let id = contract.id.clone().unwrap_or_default();
+            match self.contracts.iter().find(|old| old.id == contract.id) {
+                Some(old) => keys.extend(
+                    old.changed_keys(contract)
+                        .into_iter()
+                        .map(|key| format!("{}.{}", id, key)),
+                ),
+                None => keys.push(format!("contract {} (new)", id)),
+            }
+        }
+        for old in &self.contracts {
+            if !reloaded.contracts.iter().any(|c| c.id == old.id) {
+                keys.push(format!(
+                    "contract {} (removed)",
+                    old.id.clone().unwrap_or_default()
+                ));
+            }
+        }
+        keys
+    }
+
+    /// Checks the configuration and fills in missing contract IDs
+    fn validate(&mut self) -> Result<(), String> {
+        if self.contracts.is_empty() {
+            return Err("No contracts declared".to_string());
+        }
+
+        let mut ids = HashSet::new();
+        for (index, contract) in self.contracts.iter_mut().enumerate() {
+            let id = contract
+                .id
+                .get_or_insert_with(|| format!("c{}", index + 1))
+                .clone();
+            if !ids.insert(id.clone()) {
+                return Err(format!("Duplicate contract id {}", id));
+            }
+            if contract.targets.is_empty() {
+                return Err(format!("Contract {} has no targets", id));
+            }
+            let mut target_ids = HashSet::new();
+            for target_id in contract.targets.iter().filter_map(|t| t.id.as_ref()) {
+                if !target_ids.insert(target_id) {
+                    return Err(format!(
+                        "Contract {} has duplicate target id {}",
+                        id, target_id
+                    ));
+                }
+            }
+            contract
+                .target_dependencies()
+                .map_err(|e| format!("Contract {}: {}", id, e))?;
+            if let Some(hang) = &contract.hang {
+                hang.validate()
+                    .map_err(|e| format!("Contract {}: {}", id, e))?;
+            }
+            if let Some(ladder) = &contract.ladder {
+                if ladder.is_empty() {
+                    return Err(format!("Contract {} has an empty ladder", id));
+                }
+                if contract.vengeance_delay.is_some() {
+                    return Err(format!(
+                        "Contract {} sets both vengeance_delay and ladder",
+                        id
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hang::DEFAULT_IDLE_TICKS;
+    use crate::process_table::MemoryTable;
+
+    #[test]
+    fn test_parse_full_config() {
+        let config = Config::parse(
+            r#"
+            protect = ["name:sshd"]
+
+            [logging]
+            level = "debug"
+            file = "/tmp/wick.log"
+
+            [[contract]]
+            id = "web"
+            dog = 10
+            targets = [100, { name = "worker", delay = 30, signal = "INT", max_rss = "512M" }]
+            tick = 1
+            max_cpu = "90%/60s"
+            max_lifetime = "30m"
+            deadline = "2030-01-01T12:00:00+02:00"
+            hang = { stuck_ticks = 3 }
+            sweep_session = true
+            ladder = [{ signal = "INT", wait = 10 }, { signal = "SIGKILL" }]
+
+            [[contract]]
+            dog = { parent = true }
+            targets = [{ pid = 300 }, { name = "redis", stop = "redis-cli shutdown" }]
+            vengeance_delay = 2
+
+            [contract.hooks]
+            timeout = 7
+            before_kill = "sync"
+            after_kill = { command = "rm -f /tmp/lock", timeout = 1 }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.logging.level.as_deref(), Some("debug"));
+        assert_eq!(config.protect, vec![ProtectRule::Name("sshd".to_string())]);
+        assert_eq!(config.contracts.len(), 2);
+
+        let web = &config.contracts[0];
+        assert_eq!(web.dog, Some(Selector::Pid(10)));
+        assert_eq!(
+            web.ladder(),
+            vec![
+                Rung::new(KillSignal::Int, Duration::from_secs(10)),
+                Rung::new(KillSignal::Kill, Duration::ZERO),
+            ]
+        );
+
+        assert_eq!(web.targets[0].ladder(&web.ladder()), None);
+        assert_eq!(
+            web.targets[1].ladder(&web.ladder()),
+            Some(vec![
+                Rung::new(KillSignal::Int, Duration::from_secs(30)),
+                Rung::new(KillSignal::Kill, Duration::ZERO),
+            ])
+        );
+
+        assert_eq!(
+            web.targets[1].limits.or(web.limits()),
+            ResourceLimits {
+                max_rss: Some(ByteSize(512 << 20)),
+                max_cpu: Some(CpuLimit {
+                    percent: 90,
+                    window: Duration::from_secs(60),
+                }),
+            }
+        );
+
+        assert_eq!(web.max_lifetime, Some(Duration::from_secs(1800)));
+        assert_eq!(
+            web.deadline.map(|at| at.to_rfc3339()).as_deref(),
+            Some("2030-01-01T12:00:00+02:00")
+        );
+
+        assert_eq!(
+            web.hang,
+            Some(HangDetection {
+                stuck_ticks: 3,
+                idle_ticks: DEFAULT_IDLE_TICKS,
+            })
+        );
+
+        let second = &config.contracts[1];
+        assert_eq!(second.id.as_deref(), Some("c2"));
+        assert!(second.limits().is_empty());
+        assert_eq!((second.max_lifetime, second.deadline), (None, None));
+        assert_eq!(second.hang, None);
+        assert!(web.sweep_session && !second.sweep_session);
+        assert_eq!(second.dog, Some(Selector::Parent));
+        assert_eq!(
+            second.targets[1].stop,
+            Some(GracefulStop::Command("redis-cli shutdown".to_string()))
+        );
+        assert_eq!(second.ladder(), default_ladder(Duration::from_secs(2)));
+        assert_eq!(second.tick(), Duration::from_secs(DEFAULT_TICK));
+        assert_eq!(
+            second.hooks.hooks(),
+            Hooks {
+                on_dog_death: None,
+                before_kill: Some(Hook::new("sync", Duration::from_secs(7))),
+                after_kill: Some(Hook::new("rm -f /tmp/lock", Duration::from_secs(1))),
+            }
+        );
+        assert!(web.hooks.hooks().is_empty());
+    }
+
+    #[test]
+    fn test_invalid_configs() {
+        assert!(Config::parse("").is_err());
+        assert!(Config::parse("protect = [\"user:root\"]\n[[contract]]\ntargets = [1]").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = []").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = [1]\nbogus = 1").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = [{ pid = 1, name = \"x\" }]").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = [1]\nladder = []").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = [{ stop = \"true\" }]").is_err());
+        assert!(
+            Config::parse("[[contract]]\ndog = { pid = 1, stop = \"true\" }\ntargets = [1]")
+                .is_err()
+        );
+        assert!(Config::parse(
+            "[[contract]]\ntargets = [1]\nvengeance_delay = 1\nladder = [{ signal = \"TERM\" }]"
+        )
+        .is_err());
+        assert!(Config::parse(
+            "[[contract]]\nid = \"a\"\ntargets = [1]\n[[contract]]\nid = \"a\"\ntargets = [2]"
+        )
+        .is_err());
+        assert!(
+            Config::parse("[[contract]]\ntargets = [1]\nladder = [{ signal = \"NOPE\" }]").is_err()
+        );
+        assert!(Config::parse("[[contract]]\ntargets = [1]\nmax_lifetime = \"soon\"").is_err());
+        assert!(Config::parse("[[contract]]\ntargets = [1]\ndeadline = \"tomorrow\"").is_err());
+        assert_eq!(
+            Config::parse("[[contract]]\ntargets = [1]\nhang = { stuck_ticks = 0 }").unwrap_err(),
+            "Contract c1: hang.stuck_ticks must be at least 1"
+        );
+    }
+
+    #[test]
+    fn test_target_dependencies() {
+        let config = Config::parse(
+            r#"
+            [[contract]]
+            targets = [
+                { name = "api", id = "api", before = ["db"] },
+                { name = "db", id = "db" },
+                { name = "storage", after = ["db"] },
+            ]
+            "#,
+        )
+        .unwrap();
+        let contract = &config.contracts[0];
+
+        let table = MemoryTable::new();
+        table.spawn_named(10, 1, "api");
+        table.spawn_named(11, 1, "api");
+        table.spawn_named(20, 1, "db");
+        table.spawn_named(30, 1, "storage");
+        let resolved = contract.resolve_targets(&table).unwrap();
+        let after = contract.resolve_dependencies(&resolved).unwrap();
+        assert_eq!(after[&20], BTreeSet::from([10, 11]));
+        assert_eq!(after[&30], BTreeSet::from([20]));
+
+        let cycle = "[[contract]]\ntargets = [{ pid = 1, after = [2] }, { pid = 2, after = [1] }]";
+        assert_eq!(
+            Config::parse(cycle).unwrap_err(),
+            "Contract c1: Dependency cycle between targets"
+        );
+        let unknown = "[[contract]]\ntargets = [{ pid = 1, after = [\"db\"] }]";
+        assert_eq!(
+            Config::parse(unknown).unwrap_err(),
+            "Contract c1: Unknown target db"
+        );
+    }
+
+    #[test]
+    fn test_map_pids() {
+        let mut config = Config::parse(
+            "[[contract]]\ndog = 5\ntargets = [{ pid = 1, after = [2] }, 2, { name = \"db\" }]",
+        )
+        .unwrap();
+        let contract = &mut config.contracts[0];
+        contract.map_pids(|pid| Ok(pid + 100)).unwrap();
+        assert_eq!(contract.dog, Some(Selector::Pid(105)));
+        assert_eq!(contract.targets[0].selector, Selector::Pid(101));
+        assert_eq!(contract.targets[0].after, vec![TargetRef::Pid(102)]);
+        assert_eq!(contract.targets[1].selector, Selector::Pid(102));
+        assert_eq!(
+            contract.targets[2].selector,
+            Selector::Name("db".to_string())
+        );
+        assert!(contract.target_dependencies().is_ok());
+
+        assert_eq!(
+            contract.map_pids(|pid| Err(format!("No PID {}", pid))),
+            Err("No PID 105".to_string())
+        );
+    }
+
+    #[test]
+    fn test_restart_keys() {
+        let running = Config::parse(
+            "[[contract]]\ntargets = [{ name = \"api\", id = \"api\" }, 7]\n\
+             [[contract]]\ntargets = [8]",
+        )
+        .unwrap();
+        assert!(running.restart_keys(&running).is_empty());
+
+        // New selectors alone apply live
+        let selectors = Config::parse(
+            "[[contract]]\ntargets = [{ name = \"api\", id = \"api\" }, 9, { name = \"db\" }]\n\
+             [[contract]]\ntargets = [8]",
+        )
+        .unwrap();
+        assert!(running.restart_keys(&selectors).is_empty());
+
+        let changed = Config::parse(
+            "protect = [\"uid:0\"]\n\
+             [[contract]]\ntick = 2\nsweep_session = true\n\
+             targets = [{ name = \"api\", id = \"api\", delay = 3 }]\n\
+             [[contract]]\nid = \"extra\"\ntargets = [8]",
+        )
+        .unwrap();
+        assert_eq!(
+            running.restart_keys(&changed),
+            vec![
+                "protect",
+                "c1.targets",
+                "c1.tick",
+                "c1.sweep_session",
+                "contract extra (new)",
+                "contract c2 (removed)",
+            ]
+        );
+
+        let api = &running.contracts[0].targets[0];
+        assert!(api.has_settings());
+        assert!(!running.contracts[0].targets[1].has_settings());
+        assert!(api.same_settings(&selectors.contracts[0].targets[0]));
+        assert!(!api.same_settings(&changed.contracts[0].targets[0]));
+    }
+
+    #[test]
+    fn test_resolve_selectors() {
+        let table = MemoryTable::new();
+        table.spawn_named(10, 1, "server");
+        table.spawn_named(11, 10, "worker");
+        table.spawn_named(12, 10, "worker");
+
+        let config = Config::parse(
+            "[[contract]]\ndog = { name = \"server\" }\ntargets = [{ name = \"worker\" }, 11, 20]",
+        )
+        .unwrap();
+        let contract = &config.contracts[0];
+        assert_eq!(contract.resolve_dog(&table), Ok(10));
+        let targets = contract.resolve_targets(&table).unwrap();
+        assert_eq!(
+            targets.keys().copied().collect::<Vec<_>>(),
+            vec![11, 12, 20]
+        );
+
+        let ambiguous = ContractConfig {
+            dog: Some(Selector::Name("worker".to_string())),
+            ..contract.clone()
+        };
+        assert!(ambiguous.resolve_dog(&table).is_err());
+        assert!(Selector::Name("missing".to_string())
+            .resolve(&table)
+            .is_err());
+    }
+}
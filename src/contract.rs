@@ -0,0 +1,983 @@
+use log::{debug, info, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::sync::broadcast;
+use tokio::task::JoinSet;
+use tokio_util::sync::CancellationToken;
+
+use crate::events::{EventBus, Trigger, WickEvent};
+use crate::hang::{DogSample, HangDetection, HangDetector};
+use crate::hooks::{HookKind, Hooks};
+use crate::limits::{LimitTracker, ResourceLimits, SysinfoProbe, UsageProbe};
+use crate::proc_events::{ProcConnector, ProcEvent};
+use crate::process_table::ProcessTable;
+use crate::vengeance::Vengeance;
+
+/// How a contract ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractOutcome {
+    /// The dog died and vengeance was executed
+    Executed,
+    /// The contract was called off before the dog died; nothing was killed
+    Disarmed,
+}
+
+/// What process-wick does when it is itself sent SIGTERM or SIGINT while watching
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SelfSignalPolicy {
+    /// Execute vengeance right away, whether the dog lives or not
+    Execute,
+    /// Disarm every contract and exit without killing anything
+    #[default]
+    Abandon,
+    /// Keep watching as if nothing happened
+    Ignore,
+}
+
+impl FromStr for SelfSignalPolicy {
+    type Err = String;
+
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.trim().to_ascii_lowercase().as_str() {
+            "execute" => Ok(SelfSignalPolicy::Execute),
+            "abandon" => Ok(SelfSignalPolicy::Abandon),
+            "ignore" => Ok(SelfSignalPolicy::Ignore),
+            _ => Err(format!(
+                "Invalid self-signal policy: {} (expected execute, abandon or ignore)",
+                policy.trim()
+            )),
+        }
+    }
+}
+
+/// The targets of a contract, shared so they can change while the dog is watched
+///
+/// The set is sealed once vengeance starts; later changes are rejected.
+#[derive(Debug, Clone, Default)]
+pub struct TargetSet {
+    inner: Arc<Mutex<TargetSetInner>>,
+}
+
+#[derive(Debug, Default)]
+struct TargetSetInner {
+    pids: BTreeSet<u32>,
+    sealed: bool,
+}
+
+impl TargetSet {
+    pub fn new(pids: impl IntoIterator<Item = u32>) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(TargetSetInner {
+                pids: pids.into_iter().collect(),
+                sealed: false,
+            })),
+        }
+    }
+
+    /// Adds a target, returning whether it was new
+    pub fn add(&self, pid: u32) -> Result<bool, String> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sealed {
+            return Err("Vengeance already started, targets are sealed".to_string());
+        }
+        Ok(inner.pids.insert(pid))
+    }
+
+    /// Removes a target, returning whether it was present
+    pub fn remove(&self, pid: u32) -> Result<bool, String> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sealed {
+            return Err("Vengeance already started, targets are sealed".to_string());
+        }
+        Ok(inner.pids.remove(&pid))
+    }
+
+    /// Replaces every target at once, returning the PIDs added and removed
+    pub fn replace(
+        &self,
+        pids: impl IntoIterator<Item = u32>,
+    ) -> Result<(Vec<u32>, Vec<u32>), String> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.sealed {
+            return Err("Vengeance already started, targets are sealed".to_string());
+        }
+        let pids: BTreeSet<u32> = pids.into_iter().collect();
+        let added = pids.difference(&inner.pids).copied().collect();
+        let removed = inner.pids.difference(&pids).copied().collect();
+        inner.pids = pids;
+        Ok((added, removed))
+    }
+
+    /// Current targets in ascending PID order
+    pub fn pids(&self) -> Vec<u32> {
+        self.inner.lock().unwrap().pids.iter().copied().collect()
+    }
+
+    /// Freezes the set and returns its final contents
+    pub fn seal(&self) -> Vec<u32> {
+        let mut inner = self.inner.lock().unwrap();
+        inner.sealed = true;
+        inner.pids.iter().copied().collect()
+    }
+
+    pub fn is_sealed(&self) -> bool {
+        self.inner.lock().unwrap().sealed
+    }
+}
+
+/// A dog to watch and the targets to take down once it dies
+pub struct Contract {
+    dog_pid: u32,
+    targets: TargetSet,
+    tick: Duration,
+    vengeance: Vengeance,
+    hooks: Hooks,
+    cancel: CancellationToken,
+    fire: CancellationToken,
+    proc_events: Option<Arc<ProcConnector>>,
+    limits: HashMap<u32, ResourceLimits>,
+    probe: Arc<dyn UsageProbe>,
+    tracker: Mutex<LimitTracker>,
+    /// Vengeance on targets that went over budget, running alongside the watch
+    takedowns: Mutex<JoinSet<()>>,
+    deadline: Option<SystemTime>,
+    hang: Option<HangDetection>,
+    /// Where the dog is watched, if not in vengeance's table
+    watch_table: Option<Arc<dyn ProcessTable>>,
+}
+
+impl Contract {
+    /// Records the targets and their descendants right away, see [`Vengeance::track`]
+    pub fn new(dog_pid: u32, targets: Vec<u32>, vengeance: Vengeance) -> Self {
+        vengeance.track(&targets);
+        Self {
+            dog_pid,
+            targets: TargetSet::new(targets),
+            tick: Duration::from_secs(3),
+            vengeance,
+            hooks: Hooks::default(),
+            cancel: CancellationToken::new(),
+            fire: CancellationToken::new(),
+            proc_events: None,
+            limits: HashMap::new(),
+            probe: Arc::new(SysinfoProbe::new()),
+            tracker: Mutex::new(LimitTracker::new()),
+            takedowns: Mutex::new(JoinSet::new()),
+            deadline: None,
+            hang: None,
+            watch_table: None,
+        }
+    }
+
+    /// Sets how often the dog is checked
+    pub fn with_tick(mut self, tick: Duration) -> Self {
+        self.tick = tick;
+        self
+    }
+
+    /// Commands to run around the vengeance sequence
+    pub fn with_hooks(mut self, hooks: Hooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Uses the given token to disarm the contract
+    pub fn with_cancellation(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    /// Uses the given token to execute vengeance right away, whether the dog lives or not
+    pub fn with_firing(mut self, fire: CancellationToken) -> Self {
+        self.fire = fire;
+        self
+    }
+
+    /// Follows fork and exit events between ticks
+    ///
+    /// New descendants are tracked as soon as they fork, and the dog's exit is
+    /// acted on immediately instead of at the next tick.
+    pub fn with_proc_connector(mut self, connector: Arc<ProcConnector>) -> Self {
+        self.proc_events = Some(connector);
+        self
+    }
+
+    /// Takes `pid` down on its own, while the dog lives, once its tree goes
+    /// over one of these budgets
+    pub fn with_limits(mut self, pid: u32, limits: ResourceLimits) -> Self {
+        self.limits.insert(pid, limits);
+        self
+    }
+
+    /// Measures memory and CPU with the given probe instead of sysinfo
+    pub fn with_usage_probe(mut self, probe: Arc<dyn UsageProbe>) -> Self {
+        self.probe = probe;
+        self
+    }
+
+    /// Executes vengeance at this wall-clock time even if the dog is still alive
+    ///
+    /// The earliest of several deadlines wins.
+    pub fn with_deadline(mut self, at: SystemTime) -> Self {
+        self.deadline = Some(self.deadline.map_or(at, |current| current.min(at)));
+        self
+    }
+
+    /// Executes vengeance once the contract has been running this long, even if
+    /// the dog is still alive
+    pub fn with_max_lifetime(self, lifetime: Duration) -> Self {
+        self.with_deadline(SystemTime::now() + lifetime)
+    }
+
+    /// Counts the dog as dead once it looks hung, sampling it every tick
+    pub fn with_hang_detection(mut self, thresholds: HangDetection) -> Self {
+        self.hang = Some(thresholds);
+        self
+    }
+
+    /// Watches the dog and records the targets' descendants in this table
+    /// instead of vengeance's, e.g. a snapshot shared by many contracts
+    ///
+    /// Vengeance itself keeps signalling and confirming against its own table.
+    pub fn with_watch_table(mut self, table: Arc<dyn ProcessTable>) -> Self {
+        self.watch_table = Some(table);
+        self
+    }
+
+    fn watched(&self) -> &dyn ProcessTable {
+        self.watch_table
+            .as_ref()
+            .unwrap_or_else(|| self.vengeance.table())
+            .as_ref()
+    }
+
+    /// When vengeance runs regardless of the dog, if ever
+    pub fn deadline(&self) -> Option<SystemTime> {
+        self.deadline
+    }
+
+    /// Token that disarms the contract when cancelled
+    ///
+    /// Disarming only works while the dog is being watched; once vengeance has
+    /// started it runs to completion.
+    pub fn cancellation_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+
+    pub fn dog_pid(&self) -> u32 {
+        self.dog_pid
+    }
+
+    /// Live set of targets; changes are picked up right before vengeance
+    pub fn targets(&self) -> &TargetSet {
+        &self.targets
+    }
+
+    pub fn vengeance(&self) -> &Vengeance {
+        &self.vengeance
+    }
+
+    pub fn events(&self) -> &EventBus {
+        self.vengeance.events()
+    }
+
+    /// Subscribes to the events emitted while watching and executing
+    pub fn subscribe(&self) -> broadcast::Receiver<WickEvent> {
+        self.events().subscribe()
+    }
+
+    /// Watches the dog until it dies, the deadline passes or the contract is
+    /// fired, returning `None` if disarmed first
+    ///
+    /// Every tick also enforces the targets' budgets and records the targets' current descendants, so the ones
+    /// that are reparented before the dog dies are still taken down. With a
+    /// proc connector, forks and the dog's exit are also handled between ticks.
+    pub async fn wait_for_trigger(&self) -> Option<Trigger> {
+        // Subscribe before the first check so no exit can slip in between
+        let mut feed = self.proc_events.as_ref().map(|c| c.subscribe());
+        let mut hang = self
+            .hang
+            .map(|thresholds| HangDetector::new(self.dog_pid, thresholds));
+        loop {
+            if self.cancel.is_cancelled() {
+                return None;
+            }
+            if self.fire.is_cancelled() {
+                return Some(Trigger::Signal);
+            }
+            if !self.watched().is_alive(self.dog_pid) {
+                return Some(Trigger::DogDeath);
+            }
+            if let Some(detector) = hang.as_mut() {
+                let sample = DogSample::take(self.watched(), self.probe.as_ref(), self.dog_pid);
+                if let Some(reason) = detector.check(sample) {
+                    warn!(
+                        "🥶 Dog PID {} looks hung ({}), counting it as dead",
+                        self.dog_pid, reason
+                    );
+                    return Some(Trigger::DogHang);
+                }
+            }
+            // Recomputed every tick, so wall-clock changes are picked up
+            let until_deadline = self
+                .deadline
+                .map(|at| at.duration_since(SystemTime::now()).unwrap_or_default());
+            if until_deadline == Some(Duration::ZERO) {
+                return Some(Trigger::Deadline);
+            }
+            self.enforce_limits();
+            self.vengeance
+                .track_in(self.watched(), &self.targets.pids());
+
+            let next_tick = tokio::time::Instant::now()
+                + until_deadline.map_or(self.tick, |d| d.min(self.tick));
+            loop {
+                let received = async {
+                    match feed.as_mut() {
+                        Some(rx) => rx.recv().await,
+                        None => std::future::pending().await,
+                    }
+                };
+                let event = tokio::select! {
+                    _ = self.cancel.cancelled() => break,
+                    _ = self.fire.cancelled() => break,
+                    _ = tokio::time::sleep_until(next_tick) => break,
+                    event = received => event,
+                };
+                match event {
+                    Ok(ProcEvent::Exit { pid }) if pid == self.dog_pid => {
+                        info!("⚡ Kernel reported the exit of dog PID {}", pid);
+                        return Some(Trigger::DogDeath);
+                    }
+                    Ok(event) => self.vengeance.follow(event, &self.targets.pids()),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        warn!("⚠️ Missed {} process events, rescanning", missed);
+                        break;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => {
+                        warn!("⚠️ Process event feed closed, falling back to polling");
+                        feed = None;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Starts taking down every target whose tree went over budget, leaving
+    /// the others to the dog; `run` joins the takedowns before returning
+    fn enforce_limits(&self) {
+        for target in self.targets.pids() {
+            let Some(limits) = self.limits.get(&target) else {
+                continue;
+            };
+            if !self.vengeance.table().is_alive(target) {
+                continue;
+            }
+            let tree: Vec<u32> = self
+                .vengeance
+                .discover(target)
+                .into_iter()
+                .map(|(pid, _)| pid)
+                .collect();
+            let usage = self.probe.usage(&tree);
+            debug!(
+                "📏 Target PID {} uses {} ({} processes)",
+                target,
+                usage,
+                tree.len()
+            );
+            let breach = self
+                .tracker
+                .lock()
+                .unwrap()
+                .check(target, limits, usage, Instant::now());
+            let Some(limit) = breach else {
+                continue;
+            };
+            // Sealed means vengeance already owns the target
+            if self.targets.remove(target) != Ok(true) {
+                continue;
+            }
+            warn!(
+                "📈 Target PID {} exceeded {} ({}). Taking it down.",
+                target, limit, usage
+            );
+            self.events().emit(WickEvent::LimitExceeded {
+                target,
+                limit,
+                usage,
+            });
+            self.events().emit(WickEvent::Triggered {
+                trigger: Trigger::ResourceLimit,
+                targets: vec![target],
+            });
+            self.tracker.lock().unwrap().forget(target);
+            // The dog and the other budgets keep being checked meanwhile
+            let vengeance = self.vengeance.clone();
+            self.takedowns
+                .lock()
+                .unwrap()
+                .spawn(async move { vengeance.execute(&[target]).await });
+        }
+    }
+
+    /// Waits for every over-budget target's vengeance to finish
+    async fn join_takedowns(&self) {
+        let mut takedowns = std::mem::take(&mut *self.takedowns.lock().unwrap());
+        while takedowns.join_next().await.is_some() {}
+    }
+
+    /// Watches the dog until it dies or the deadline passes, then executes
+    /// vengeance on the targets
+    pub async fn run(&self) -> ContractOutcome {
+        let Some(trigger) = self.wait_for_trigger().await else {
+            info!("🕊️ Contract disarmed. Standing down without killing anything.");
+            self.events().emit(WickEvent::Disarmed);
+            self.join_takedowns().await;
+            return ContractOutcome::Disarmed;
+        };
+
+        match trigger {
+            Trigger::DogDeath => {
+                warn!("💀 Dog died. Unleashing vengeance.");
+                self.events().emit(WickEvent::DogDied { pid: self.dog_pid });
+            }
+            Trigger::DogHang => warn!("💀 Dog hung. Unleashing vengeance."),
+            Trigger::Signal => warn!(
+                "📡 Told to stop while dog PID {} is still alive. Unleashing vengeance.",
+                self.dog_pid
+            ),
+            _ => warn!(
+                "⏰ Deadline reached while dog PID {} is still alive. Unleashing vengeance.",
+                self.dog_pid
+            ),
+        }
+        self.events().emit(WickEvent::Triggered {
+            trigger,
+            targets: self.targets.pids(),
+        });
+        if matches!(trigger, Trigger::DogDeath | Trigger::DogHang) {
+            self.run_hook(HookKind::OnDogDeath, &self.targets.pids())
+                .await;
+        }
+        let outcome = self.execute().await;
+        self.join_takedowns().await;
+        outcome
+    }
+
+    /// Executes vengeance on the targets right away, without waiting for the dog
+    ///
+    /// Runs the before-kill and after-kill hooks around it; the targets are
+    /// sealed first.
+    pub async fn execute(&self) -> ContractOutcome {
+        let targets = self.targets.seal();
+        info!("🎯 Final targets: {:?}", targets);
+        self.run_hook(HookKind::BeforeKill, &targets).await;
+        self.vengeance.execute(&targets).await;
+        self.run_hook(HookKind::AfterKill, &targets).await;
+
+        info!("🧘 Process-wick retires in peace.");
+        self.events().emit(WickEvent::Retired);
+        ContractOutcome::Executed
+    }
+
+    /// Hooks run around the vengeance sequence
+    pub fn hooks(&self) -> &Hooks {
+        &self.hooks
+    }
+
+    async fn run_hook(&self, kind: HookKind, targets: &[u32]) {
+        if let Some(hook) = self.hooks.get(kind) {
+            let outcome = hook.run(kind, self.dog_pid, targets).await;
+            self.events().emit(WickEvent::HookFinished {
+                hook: kind,
+                exit_code: outcome.exit_code,
+                timed_out: outcome.timed_out,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::{MemoryProbe, Usage};
+    use crate::process_table::{MemoryTable, ProcessInfo, ProcessTable};
+    use crate::reaper::{KillSignal, RecordingReaper};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_run_emits_events_in_order() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        let contract = Contract::new(50, vec![100], vengeance);
+        let mut rx = contract.subscribe();
+
+        assert_eq!(contract.run().await, ContractOutcome::Executed);
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        assert_eq!(
+            events,
+            vec![
+                WickEvent::DogDied { pid: 50 },
+                WickEvent::Triggered {
+                    trigger: Trigger::DogDeath,
+                    targets: vec![100],
+                },
+                WickEvent::SignalSent {
+                    pid: 100,
+                    signal: KillSignal::Term,
+                    group: true
+                },
+                WickEvent::ProcessExited { pid: 100 },
+                WickEvent::SignalSent {
+                    pid: 100,
+                    signal: KillSignal::Kill,
+                    group: true
+                },
+                WickEvent::Retired,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_orphaned_descendants_are_killed() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        table.spawn(101, 100);
+        table.spawn(102, 101);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone());
+        let contract =
+            Arc::new(Contract::new(50, vec![100], vengeance).with_tick(Duration::from_millis(10)));
+        let running = tokio::spawn({
+            let contract = contract.clone();
+            async move { contract.run().await }
+        });
+
+        // 102 is seen under the target, then its parent exits and it is reparented
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        table.remove(101);
+        table.spawn(102, 1);
+        table.remove(50);
+        assert_eq!(running.await.unwrap(), ContractOutcome::Executed);
+
+        assert!(reaper
+            .calls()
+            .iter()
+            .any(|call| call.pid == 102 && !call.group));
+        assert!(!table.is_alive(102));
+    }
+
+    #[tokio::test]
+    async fn test_proc_events_follow_forks_and_dog_exit() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone());
+        let (connector, kernel) = ProcConnector::detached();
+        let contract = Arc::new(
+            Contract::new(50, vec![100], vengeance)
+                .with_tick(Duration::from_secs(60))
+                .with_proc_connector(Arc::new(connector)),
+        );
+        let running = tokio::spawn({
+            let contract = contract.clone();
+            async move { contract.run().await }
+        });
+
+        // A child forks a daemon and exits between two ticks
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        table.spawn(101, 100);
+        kernel
+            .send(ProcEvent::Fork {
+                parent: 100,
+                child: 101,
+            })
+            .unwrap();
+        table.spawn(102, 101);
+        kernel
+            .send(ProcEvent::Fork {
+                parent: 101,
+                child: 102,
+            })
+            .unwrap();
+        table.remove(101);
+        table.spawn(102, 1);
+        kernel.send(ProcEvent::Exit { pid: 101 }).unwrap();
+
+        // The dog's exit is acted on long before the next tick
+        kernel.send(ProcEvent::Exit { pid: 50 }).unwrap();
+        let outcome = tokio::time::timeout(Duration::from_secs(5), running)
+            .await
+            .unwrap();
+        assert_eq!(outcome.unwrap(), ContractOutcome::Executed);
+        assert!(reaper
+            .calls()
+            .iter()
+            .any(|call| call.pid == 102 && !call.group));
+        assert!(!table.is_alive(102));
+    }
+
+    #[tokio::test]
+    async fn test_target_over_budget_is_taken_down_alone() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        table.spawn(101, 100);
+        table.spawn(200, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone());
+        let probe = Arc::new(MemoryProbe::new());
+        let bloated = Usage {
+            rss_bytes: 3 << 30,
+            cpu_percent: 12,
+        };
+        probe.set(101, bloated);
+        let limits = ResourceLimits {
+            max_rss: Some("2G".parse().unwrap()),
+            max_cpu: None,
+        };
+        let contract = Arc::new(
+            Contract::new(50, vec![100, 200], vengeance)
+                .with_tick(Duration::from_millis(10))
+                .with_limits(100, limits)
+                .with_limits(200, limits)
+                .with_usage_probe(probe),
+        );
+        let mut rx = contract.subscribe();
+        let running = tokio::spawn({
+            let contract = contract.clone();
+            async move { contract.run().await }
+        });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(!table.is_alive(100) && !table.is_alive(101));
+        assert!(table.is_alive(200));
+        assert_eq!(contract.targets().pids(), vec![200]);
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            WickEvent::LimitExceeded {
+                target: 100,
+                limit: "RSS 2.0G".to_string(),
+                usage: bloated,
+            }
+        );
+
+        table.remove(50);
+        assert_eq!(running.await.unwrap(), ContractOutcome::Executed);
+        assert!(!table.is_alive(200));
+    }
+
+    #[tokio::test]
+    async fn test_dog_is_watched_while_a_target_is_taken_down() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        table.spawn(200, 1);
+        // 100 ignores SIGTERM, so its takedown sits through the whole grace period
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery()
+                .with_stubborn(100),
+        );
+        let vengeance = Vengeance::new(Duration::from_secs(2))
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        let probe = Arc::new(MemoryProbe::new());
+        probe.set(
+            100,
+            Usage {
+                rss_bytes: 3 << 30,
+                cpu_percent: 0,
+            },
+        );
+        let contract = Contract::new(50, vec![100, 200], vengeance)
+            .with_tick(Duration::from_millis(10))
+            .with_limits(
+                100,
+                ResourceLimits {
+                    max_rss: Some("2G".parse().unwrap()),
+                    max_cpu: None,
+                },
+            )
+            .with_usage_probe(probe);
+        let mut rx = contract.subscribe();
+        let running = tokio::spawn(async move { contract.run().await });
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        table.remove(50);
+        let dog_died = tokio::time::timeout(Duration::from_secs(1), async {
+            loop {
+                if let WickEvent::DogDied { pid } = rx.recv().await.unwrap() {
+                    return pid;
+                }
+            }
+        });
+        assert_eq!(dog_died.await, Ok(50));
+        assert!(
+            table.is_alive(100),
+            "the takedown is still in its grace period"
+        );
+
+        assert_eq!(running.await.unwrap(), ContractOutcome::Executed);
+        assert!(!table.is_alive(100) && !table.is_alive(200));
+    }
+
+    #[tokio::test]
+    async fn test_deadline_fires_while_dog_lives() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        // The tick is far longer than the lifetime: the wait is cut short
+        let contract = Contract::new(50, vec![100], vengeance)
+            .with_tick(Duration::from_secs(60))
+            .with_max_lifetime(Duration::from_millis(50))
+            .with_deadline(SystemTime::now() + Duration::from_secs(3600));
+        let mut rx = contract.subscribe();
+
+        let outcome = tokio::time::timeout(Duration::from_secs(5), contract.run())
+            .await
+            .unwrap();
+        assert_eq!(outcome, ContractOutcome::Executed);
+        assert!(table.is_alive(50));
+        assert!(!table.is_alive(100));
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            WickEvent::Triggered {
+                trigger: Trigger::Deadline,
+                targets: vec![100],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_hung_dog_counts_as_dead() {
+        let table = Arc::new(MemoryTable::new());
+        table.insert(ProcessInfo::new(50, 1, 0).with_state('S'));
+        // An exited child the dog never reaps
+        table.insert(ProcessInfo::new(51, 50, 0).with_state('Z'));
+        table.spawn(100, 1);
+        let probe = Arc::new(MemoryProbe::new());
+        probe.set_cpu_time(50, Duration::from_millis(40));
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        let contract = Contract::new(50, vec![100], vengeance)
+            .with_tick(Duration::from_millis(10))
+            .with_usage_probe(probe)
+            .with_hang_detection(HangDetection {
+                stuck_ticks: 100,
+                idle_ticks: 3,
+            });
+        let mut rx = contract.subscribe();
+
+        let outcome = tokio::time::timeout(Duration::from_secs(5), contract.run())
+            .await
+            .unwrap();
+        assert_eq!(outcome, ContractOutcome::Executed);
+        assert!(table.is_alive(50));
+        assert!(!table.is_alive(100));
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            WickEvent::Triggered {
+                trigger: Trigger::DogHang,
+                targets: vec![100],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_firing_executes_while_dog_lives() {
+        assert_eq!("Execute".parse(), Ok(SelfSignalPolicy::Execute));
+        assert_eq!("ignore".parse(), Ok(SelfSignalPolicy::Ignore));
+        assert!("flee".parse::<SelfSignalPolicy>().is_err());
+
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        let fire = CancellationToken::new();
+        let contract = Contract::new(50, vec![100], vengeance)
+            .with_tick(Duration::from_secs(60))
+            .with_firing(fire.clone());
+        let mut rx = contract.subscribe();
+
+        let run = tokio::spawn(async move { contract.run().await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        fire.cancel();
+        let outcome = tokio::time::timeout(Duration::from_secs(5), run)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(outcome, ContractOutcome::Executed);
+        assert!(table.is_alive(50));
+        assert!(!table.is_alive(100));
+        assert_eq!(
+            rx.recv().await.unwrap(),
+            WickEvent::Triggered {
+                trigger: Trigger::Signal,
+                targets: vec![100],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_disarms_without_killing() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        let reaper = Arc::new(RecordingReaper::new().with_table(table.clone()));
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone());
+        let contract = Contract::new(50, vec![100], vengeance).with_tick(Duration::from_secs(60));
+        let mut rx = contract.subscribe();
+
+        let token = contract.cancellation_token();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            token.cancel();
+        });
+
+        assert_eq!(contract.run().await, ContractOutcome::Disarmed);
+        assert_eq!(rx.try_recv().unwrap(), WickEvent::Disarmed);
+        assert!(reaper.calls().is_empty());
+        assert!(table.is_alive(100));
+    }
+
+    #[tokio::test]
+    async fn test_targets_changed_while_watching() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(50, 1);
+        table.spawn(100, 1);
+        table.spawn(101, 1);
+        let reaper = Arc::new(RecordingReaper::new().with_table(table.clone()));
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone());
+        let contract = Contract::new(50, vec![100], vengeance).with_tick(Duration::from_millis(10));
+
+        let targets = contract.targets().clone();
+        assert_eq!(targets.add(101), Ok(true));
+        assert_eq!(targets.remove(100), Ok(true));
+        table.remove(50);
+
+        assert_eq!(contract.run().await, ContractOutcome::Executed);
+        assert!(table.is_alive(100));
+        assert!(!table.is_alive(101));
+        assert!(targets.add(102).is_err());
+        assert!(targets.replace([102]).is_err());
+        assert_eq!(targets.pids(), vec![101]);
+    }
+
+    #[test]
+    fn test_replace_targets() {
+        let targets = TargetSet::new([1, 2, 3]);
+        assert_eq!(targets.replace([2, 3, 4, 5]), Ok((vec![4, 5], vec![1])));
+        assert_eq!(targets.pids(), vec![2, 3, 4, 5]);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hooks_run_around_vengeance() {
+        use crate::hooks::Hook;
+
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper);
+        let hook = |code: i32| Some(Hook::new(format!("exit {}", code), Duration::from_secs(5)));
+        let contract = Contract::new(50, vec![100], vengeance).with_hooks(Hooks {
+            on_dog_death: hook(0),
+            before_kill: hook(1),
+            after_kill: hook(2),
+        });
+        let mut rx = contract.subscribe();
+
+        assert_eq!(contract.run().await, ContractOutcome::Executed);
+
+        let mut events = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            events.push(event);
+        }
+        let hooks: Vec<(usize, HookKind, Option<i32>)> = events
+            .iter()
+            .enumerate()
+            .filter_map(|(index, event)| match event {
+                WickEvent::HookFinished {
+                    hook, exit_code, ..
+                } => Some((index, *hook, *exit_code)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            hooks,
+            vec![
+                (2, HookKind::OnDogDeath, Some(0)),
+                (3, HookKind::BeforeKill, Some(1)),
+                (events.len() - 2, HookKind::AfterKill, Some(2)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,866 @@
+use log::{info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::os::unix::fs::FileTypeExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{ContractConfig, HooksConfig, Selector};
+use crate::contract::{Contract, ContractOutcome, TargetSet};
+use crate::process_table::{native_table, MemoryTable, ProcessTable};
+use crate::safety::Safety;
+
+fn default_vengeance_delay() -> u64 {
+    5
+}
+
+/// A request sent to the daemon, one JSON object per line
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "cmd", rename_all = "snake_case")]
+pub enum Request {
+    /// Starts watching `dog`; `targets` die when it does
+    Register {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        id: Option<String>,
+        dog: u32,
+        targets: Vec<u32>,
+        #[serde(default = "default_vengeance_delay")]
+        vengeance_delay: u64,
+        #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+        hooks: HooksConfig,
+    },
+    /// Disarms a contract: it is forgotten without killing anything
+    Remove { id: String },
+    /// Adds a target to a contract that is still being watched
+    AddTarget { id: String, pid: u32 },
+    /// Removes a target from a contract that is still being watched
+    RemoveTarget { id: String, pid: u32 },
+    /// Lists every contract the daemon holds
+    List,
+}
+
+/// Where a contract is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractState {
+    Watching,
+    Executing,
+}
+
+/// A contract as reported by `list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractInfo {
+    pub id: String,
+    pub dog: u32,
+    pub targets: Vec<u32>,
+    pub vengeance_delay: u64,
+    pub state: ContractState,
+}
+
+/// The daemon's answer to a [`Request`]
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct Response {
+    pub ok: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub contracts: Option<Vec<ContractInfo>>,
+}
+
+impl Response {
+    pub fn ok() -> Self {
+        Self {
+            ok: true,
+            ..Self::default()
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            ok: false,
+            error: Some(message.into()),
+            ..Self::default()
+        }
+    }
+}
+
+/// Builds the contract for a registration, the same way watch mode arms one
+pub type Arm = dyn Fn(ContractConfig) -> Result<Contract, String> + Send + Sync;
+
+type Registry = Arc<Mutex<BTreeMap<String, Arc<Contract>>>>;
+
+/// A single long-lived wick holding many dog→targets contracts
+///
+/// Each registration is armed into a full [`Contract`] with its hooks, events,
+/// lineage tracking and deny list. All of them watch their dogs in one process
+/// snapshot taken per tick instead of each polling on its own.
+pub struct Daemon {
+    contracts: Registry,
+    next_id: AtomicU64,
+    table: Arc<dyn ProcessTable>,
+    snapshot: Arc<MemoryTable>,
+    arm: Box<Arm>,
+    tick: Duration,
+}
+
+impl Daemon {
+    pub fn new(
+        tick: Duration,
+        arm: impl Fn(ContractConfig) -> Result<Contract, String> + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            contracts: Arc::new(Mutex::new(BTreeMap::new())),
+            next_id: AtomicU64::new(1),
+            table: native_table(),
+            snapshot: Arc::new(MemoryTable::new()),
+            arm: Box::new(arm),
+            tick,
+        }
+    }
+
+    /// Takes the per-tick snapshots from the given table instead of the system's
+    pub fn with_table(mut self, table: Arc<dyn ProcessTable>) -> Self {
+        self.table = table;
+        self
+    }
+
+    /// Applies a single request to the contract registry
+    ///
+    /// Registered contracts start watching right away, so this must be called
+    /// from within a Tokio runtime.
+    pub fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::Register {
+                id,
+                dog,
+                targets,
+                vengeance_delay,
+                hooks,
+            } => {
+                if targets.is_empty() {
+                    return Response::error("No valid PIDs provided");
+                }
+                if dog == 0 {
+                    return Response::error("Dog PID 0 is not a process");
+                }
+                let id = id.unwrap_or_else(|| {
+                    let contracts = self.contracts.lock().unwrap();
+                    loop {
+                        let candidate = format!("c{}", self.next_id.fetch_add(1, Ordering::SeqCst));
+                        if !contracts.contains_key(&candidate) {
+                            break candidate;
+                        }
+                    }
+                });
+                if self.contracts.lock().unwrap().contains_key(&id) {
+                    return Response::error(format!("Contract {} already exists", id));
+                }
+
+                // Armed outside the lock, resolving targets scans the processes
+                let config = ContractConfig {
+                    id: Some(id.clone()),
+                    dog: Some(Selector::Pid(dog)),
+                    targets: targets
+                        .iter()
+                        .map(|&pid| Selector::Pid(pid).into())
+                        .collect(),
+                    vengeance_delay: Some(vengeance_delay),
+                    hooks,
+                    ..ContractConfig::default()
+                };
+                let contract = match (self.arm)(config) {
+                    Ok(contract) => contract,
+                    Err(e) => return Response::error(e),
+                };
+                if let Err(e) = self.check_targets(&contract, &targets) {
+                    return Response::error(e);
+                }
+                // The new dog has to be in the snapshot before its first check
+                self.snapshot.refresh_from(self.table.as_ref());
+                let contract = Arc::new(
+                    contract
+                        .with_tick(self.tick)
+                        .with_watch_table(self.snapshot.clone()),
+                );
+
+                {
+                    let mut contracts = self.contracts.lock().unwrap();
+                    if contracts.contains_key(&id) {
+                        return Response::error(format!("Contract {} already exists", id));
+                    }
+                    contracts.insert(id.clone(), contract.clone());
+                }
+                info!(
+                    "📝 Contract {} registered: dog {}, targets {:?}",
+                    id, dog, targets
+                );
+                tokio::spawn(Self::run(self.contracts.clone(), id.clone(), contract));
+
+                Response {
+                    id: Some(id),
+                    ..Response::ok()
+                }
+            }
+            Request::Remove { id } => {
+                let mut contracts = self.contracts.lock().unwrap();
+                match contracts.get(&id).map(|c| c.targets().is_sealed()) {
+                    Some(false) => {
+                        if let Some(contract) = contracts.remove(&id) {
+                            contract.cancellation_token().cancel();
+                        }
+                        info!("🕊️ Contract {} disarmed. Nothing will be killed.", id);
+                        Response {
+                            id: Some(id),
+                            ..Response::ok()
+                        }
+                    }
+                    Some(true) => Response::error(format!("Contract {} is already executing", id)),
+                    None => Response::error(format!("Unknown contract: {}", id)),
+                }
+            }
+            Request::AddTarget { id, pid } => self.update_targets(id, |contract| {
+                self.check_targets(contract, &[pid])?;
+                contract.targets().add(pid)?;
+                info!("➕ Target {} added at runtime", pid);
+                Ok(())
+            }),
+            Request::RemoveTarget { id, pid } => self.update_targets(id, |contract| {
+                if !contract.targets().remove(pid)? {
+                    return Err(format!("PID {} is not a target", pid));
+                }
+                info!("➖ Target {} removed at runtime", pid);
+                Ok(())
+            }),
+            Request::List => Response {
+                contracts: Some(
+                    self.contracts
+                        .lock()
+                        .unwrap()
+                        .iter()
+                        .map(|(id, contract)| ContractInfo::of(id, contract))
+                        .collect(),
+                ),
+                ..Response::ok()
+            },
+        }
+    }
+
+    /// Rejects targets the contract's deny list refuses
+    fn check_targets(&self, contract: &Contract, targets: &[u32]) -> Result<(), String> {
+        let safety = contract.vengeance().safety();
+        for &pid in targets {
+            if let Err(reason) = safety.check(self.table.as_ref(), pid) {
+                return Err(format!("Refusing target {}: {}", pid, reason));
+            }
+        }
+        Ok(())
+    }
+
+    fn update_targets(
+        &self,
+        id: String,
+        update: impl FnOnce(&Contract) -> Result<(), String>,
+    ) -> Response {
+        let Some(contract) = self.contracts.lock().unwrap().get(&id).cloned() else {
+            return Response::error(format!("Unknown contract: {}", id));
+        };
+        if contract.targets().is_sealed() {
+            return Response::error(format!("Contract {} is already executing", id));
+        }
+
+        match update(&contract) {
+            Ok(()) => Response {
+                id: Some(id),
+                ..Response::ok()
+            },
+            Err(e) => Response::error(e),
+        }
+    }
+
+    /// Watches a registered contract until it is executed or disarmed, then forgets it
+    async fn run(contracts: Registry, id: String, contract: Arc<Contract>) {
+        let outcome = contract.run().await;
+
+        let mut contracts = contracts.lock().unwrap();
+        // A removed contract's ID may already belong to a new registration
+        if contracts
+            .get(&id)
+            .is_some_and(|current| Arc::ptr_eq(current, &contract))
+        {
+            contracts.remove(&id);
+        }
+        if outcome == ContractOutcome::Executed {
+            info!("🧘 Contract {} fulfilled.", id);
+        }
+    }
+
+    /// Takes the process snapshot every contract watches its dog in
+    ///
+    /// Skipped while no contract is watching.
+    pub fn refresh(&self) {
+        let watching = self
+            .contracts
+            .lock()
+            .unwrap()
+            .values()
+            .any(|contract| !contract.targets().is_sealed());
+        if watching {
+            self.snapshot.refresh_from(self.table.as_ref());
+        }
+    }
+
+    /// Serves the control socket and watches all contracts until cancelled
+    pub async fn serve(
+        self: Arc<Self>,
+        socket_path: PathBuf,
+        cancel: CancellationToken,
+    ) -> std::io::Result<()> {
+        let daemon = self.clone();
+        let stop = cancel.child_token();
+        let ticker_stop = stop.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(daemon.tick);
+            loop {
+                tokio::select! {
+                    _ = stop.cancelled() => break,
+                    _ = ticker.tick() => daemon.refresh(),
+                }
+            }
+        });
+
+        if let Err(e) = serve_control(socket_path, cancel, self).await {
+            ticker_stop.cancel();
+            return Err(e);
+        }
+        info!("🏠 Daemon stopped.");
+        Ok(())
+    }
+}
+
+impl ContractInfo {
+    fn of(id: &str, contract: &Contract) -> Self {
+        let state = if contract.targets().is_sealed() {
+            ContractState::Executing
+        } else {
+            ContractState::Watching
+        };
+        Self {
+            id: id.to_string(),
+            dog: contract.dog_pid(),
+            targets: contract.targets().pids(),
+            vengeance_delay: contract.vengeance().delay().as_secs(),
+            state,
+        }
+    }
+}
+
+impl ControlHandler for Daemon {
+    fn handle(&self, request: Request) -> Response {
+        Daemon::handle(self, request)
+    }
+}
+
+/// Something that answers control requests
+pub trait ControlHandler: Send + Sync {
+    fn handle(&self, request: Request) -> Response;
+}
+
+/// Name under which a watch-mode contract is exposed on its control socket
+pub const WATCH_CONTRACT_ID: &str = "default";
+
+/// Exposes a single watch-mode [`Contract`] over the control protocol
+///
+/// The contract is reported as `"default"`. Targets can be added and removed
+/// until vengeance starts, and removing the contract disarms it.
+pub struct ContractControl {
+    table: Arc<dyn ProcessTable>,
+    safety: Safety,
+    dog_pid: u32,
+    vengeance_delay: u64,
+    targets: TargetSet,
+    cancel: CancellationToken,
+}
+
+impl ContractControl {
+    pub fn new(contract: &Contract) -> Self {
+        Self {
+            table: contract.vengeance().table().clone(),
+            safety: contract.vengeance().safety().clone(),
+            dog_pid: contract.dog_pid(),
+            vengeance_delay: contract.vengeance().delay().as_secs(),
+            targets: contract.targets().clone(),
+            cancel: contract.cancellation_token(),
+        }
+    }
+
+    fn check_id(id: &str) -> Result<(), Response> {
+        if id == WATCH_CONTRACT_ID {
+            Ok(())
+        } else {
+            Err(Response::error(format!("Unknown contract: {}", id)))
+        }
+    }
+}
+
+impl ControlHandler for ContractControl {
+    fn handle(&self, request: Request) -> Response {
+        match request {
+            Request::Register { .. } => Response::error(
+                "This wick watches a single contract; run a daemon to register more",
+            ),
+            Request::Remove { id } => {
+                if let Err(response) = Self::check_id(&id) {
+                    return response;
+                }
+                if self.targets.is_sealed() {
+                    return Response::error(format!("Contract {} is already executing", id));
+                }
+                info!("🕊️ Disarm requested over the control socket.");
+                self.cancel.cancel();
+                Response {
+                    id: Some(id),
+                    ..Response::ok()
+                }
+            }
+            Request::AddTarget { id, pid } => {
+                if let Err(response) = Self::check_id(&id) {
+                    return response;
+                }
+                if let Err(reason) = self.safety.check(self.table.as_ref(), pid) {
+                    return Response::error(format!("Refusing target {}: {}", pid, reason));
+                }
+                match self.targets.add(pid) {
+                    Ok(_) => {
+                        info!("➕ Target {} added at runtime", pid);
+                        Response {
+                            id: Some(id),
+                            ..Response::ok()
+                        }
+                    }
+                    Err(e) => Response::error(e),
+                }
+            }
+            Request::RemoveTarget { id, pid } => {
+                if let Err(response) = Self::check_id(&id) {
+                    return response;
+                }
+                match self.targets.remove(pid) {
+                    Ok(true) => {
+                        info!("➖ Target {} removed at runtime", pid);
+                        Response {
+                            id: Some(id),
+                            ..Response::ok()
+                        }
+                    }
+                    Ok(false) => Response::error(format!("PID {} is not a target", pid)),
+                    Err(e) => Response::error(e),
+                }
+            }
+            Request::List => {
+                let state = if self.targets.is_sealed() {
+                    ContractState::Executing
+                } else {
+                    ContractState::Watching
+                };
+                Response {
+                    contracts: Some(vec![ContractInfo {
+                        id: WATCH_CONTRACT_ID.to_string(),
+                        dog: self.dog_pid,
+                        targets: self.targets.pids(),
+                        vengeance_delay: self.vengeance_delay,
+                        state,
+                    }]),
+                    ..Response::ok()
+                }
+            }
+        }
+    }
+}
+
+/// Serves the newline-delimited JSON control protocol on a Unix socket until cancelled
+pub async fn serve_control(
+    socket_path: PathBuf,
+    cancel: CancellationToken,
+    handler: Arc<dyn ControlHandler>,
+) -> std::io::Result<()> {
+    let socket_path = socket_path.as_path();
+    // A socket file left behind by a previous wick would make bind fail, but
+    // anything that is not a socket is never ours to delete
+    if let Ok(metadata) = std::fs::symlink_metadata(socket_path) {
+        if !metadata.file_type().is_socket() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", socket_path.display()),
+            ));
+        }
+        if UnixStream::connect(socket_path).await.is_err() {
+            std::fs::remove_file(socket_path)?;
+        }
+    }
+    let listener = UnixListener::bind(socket_path)?;
+    info!("🏠 Control socket listening on {}", socket_path.display());
+
+    loop {
+        tokio::select! {
+            _ = cancel.cancelled() => break,
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let handler = handler.clone();
+                    tokio::spawn(async move {
+                        if let Err(e) = serve_connection(handler.as_ref(), stream).await {
+                            warn!("Control connection failed: {}", e);
+                        }
+                    });
+                }
+                Err(e) => warn!("Failed to accept control connection: {}", e),
+            },
+        }
+    }
+
+    let _ = std::fs::remove_file(socket_path);
+    Ok(())
+}
+
+/// Only wick's own user and root may send requests
+fn authorize(peer_uid: u32, own_uid: u32) -> Result<(), String> {
+    if peer_uid == own_uid || peer_uid == 0 {
+        Ok(())
+    } else {
+        Err(format!(
+            "Refusing control connection from uid {}: only uid {} and root may control this wick",
+            peer_uid, own_uid
+        ))
+    }
+}
+
+async fn serve_connection(handler: &dyn ControlHandler, stream: UnixStream) -> std::io::Result<()> {
+    let peer_uid = stream.peer_cred()?.uid();
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    if let Err(e) = authorize(peer_uid, unsafe { libc::geteuid() }) {
+        warn!("🚫 {}", e);
+        let mut encoded =
+            serde_json::to_string(&Response::error(e)).map_err(std::io::Error::other)?;
+        encoded.push('\n');
+        return writer.write_all(encoded.as_bytes()).await;
+    }
+
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => handler.handle(request),
+            Err(e) => Response::error(format!("Invalid request: {}", e)),
+        };
+        let mut encoded = serde_json::to_string(&response).map_err(std::io::Error::other)?;
+        encoded.push('\n');
+        writer.write_all(encoded.as_bytes()).await?;
+    }
+    Ok(())
+}
+
+/// Sends a single request to a daemon and waits for its response
+pub async fn send_request(socket_path: &Path, request: &Request) -> std::io::Result<Response> {
+    let stream = UnixStream::connect(socket_path).await?;
+    let (reader, mut writer) = stream.into_split();
+
+    let mut encoded = serde_json::to_string(request).map_err(std::io::Error::other)?;
+    encoded.push('\n');
+    writer.write_all(encoded.as_bytes()).await?;
+
+    let mut lines = BufReader::new(reader).lines();
+    let line = lines.next_line().await?.ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            "daemon closed the connection",
+        )
+    })?;
+    serde_json::from_str(&line).map_err(std::io::Error::other)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventBus, WickEvent};
+    use crate::reaper::RecordingReaper;
+    use crate::safety::parse_protect_rules;
+    use crate::vengeance::Vengeance;
+
+    fn register(dog: u32, targets: Vec<u32>) -> Request {
+        Request::Register {
+            id: None,
+            dog,
+            targets,
+            vengeance_delay: 0,
+            hooks: HooksConfig::default(),
+        }
+    }
+
+    /// A daemon whose contracts signal and watch `table`, emitting on `events`
+    fn daemon(table: Arc<MemoryTable>, safety: Safety, events: EventBus) -> Daemon {
+        let reaper = Arc::new(RecordingReaper::new().with_table(table.clone()));
+        let processes = table.clone();
+        let arm = move |config: ContractConfig| {
+            let dog = config.resolve_dog(processes.as_ref())?;
+            let targets = config.resolve_targets(processes.as_ref())?;
+            let vengeance = Vengeance::new(config.vengeance_delay())
+                .with_table(processes.clone())
+                .with_reaper(reaper.clone())
+                .with_safety(safety.clone())
+                .with_events(events.clone());
+            Ok(Contract::new(dog, targets.into_keys().collect(), vengeance)
+                .with_hooks(config.hooks.hooks()))
+        };
+        Daemon::new(Duration::from_millis(10), arm).with_table(table)
+    }
+
+    fn table(pids: &[u32]) -> Arc<MemoryTable> {
+        let table = Arc::new(MemoryTable::new());
+        for &pid in pids {
+            table.spawn(pid, 1);
+        }
+        table
+    }
+
+    #[test]
+    fn test_request_json_shape() {
+        let request: Request =
+            serde_json::from_str(r#"{"cmd":"register","dog":1,"targets":[2,3]}"#).unwrap();
+        assert_eq!(
+            request,
+            Request::Register {
+                id: None,
+                dog: 1,
+                targets: vec![2, 3],
+                vengeance_delay: 5,
+                hooks: HooksConfig::default(),
+            }
+        );
+        assert_eq!(
+            serde_json::to_string(&Request::List).unwrap(),
+            r#"{"cmd":"list"}"#
+        );
+
+        let request: Request = serde_json::from_str(
+            r#"{"cmd":"register","dog":1,"targets":[2],"hooks":{"after_kill":"echo done"}}"#,
+        )
+        .unwrap();
+        let Request::Register { hooks, .. } = &request else {
+            panic!("expected a registration");
+        };
+        assert_eq!(hooks.after_kill.as_ref().unwrap().command, "echo done");
+        let encoded = serde_json::to_string(&request).unwrap();
+        assert_eq!(serde_json::from_str::<Request>(&encoded).unwrap(), request);
+    }
+
+    #[tokio::test]
+    async fn test_register_list_remove() {
+        let daemon = daemon(table(&[10, 11, 20, 21]), Safety::default(), EventBus::new());
+
+        let id = daemon.handle(register(10, vec![20])).id.unwrap();
+        let named = daemon.handle(Request::Register {
+            id: Some("db".to_string()),
+            dog: 11,
+            targets: vec![21],
+            vengeance_delay: 0,
+            hooks: HooksConfig::default(),
+        });
+        assert_eq!(named.id.as_deref(), Some("db"));
+
+        let listed = daemon.handle(Request::List).contracts.unwrap();
+        assert_eq!(listed.len(), 2);
+
+        assert!(daemon.handle(Request::Remove { id: id.clone() }).ok);
+        assert!(!daemon.handle(Request::Remove { id }).ok);
+        assert!(!daemon.handle(register(10, vec![])).ok);
+        assert!(!daemon.handle(register(0, vec![20])).ok);
+        assert_eq!(
+            daemon.handle(register(10, vec![20, 1])).error.as_deref(),
+            Some("Refusing target 1: PID 1 is init")
+        );
+        assert_eq!(daemon.handle(Request::List).contracts.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_only_dead_dogs_trigger_vengeance() {
+        let table = table(&[10, 11, 20, 21]);
+        // A child of target 21, orphaned before its dog dies
+        table.spawn(22, 21);
+        let events = EventBus::new();
+        let mut rx = events.subscribe();
+        let daemon = daemon(table.clone(), Safety::default(), events);
+
+        daemon.handle(register(10, vec![20]));
+        daemon.handle(register(11, vec![21]));
+        // Give the contracts a few ticks to record the child
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        table.spawn(22, 1);
+        table.remove(11);
+        daemon.refresh();
+
+        for _ in 0..100 {
+            if daemon.handle(Request::List).contracts.unwrap().len() == 1 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(!table.is_alive(21) && !table.is_alive(22));
+        assert!(table.is_alive(20));
+        let listed = daemon.handle(Request::List).contracts.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].dog, 10);
+        assert_eq!(listed[0].state, ContractState::Watching);
+
+        let mut died = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WickEvent::DogDied { pid } = event {
+                died.push(pid);
+            }
+        }
+        assert_eq!(died, vec![11]);
+    }
+
+    #[tokio::test]
+    async fn test_protect_rules_apply_to_daemon_contracts() {
+        let safety = Safety::new(parse_protect_rules("pid:21").unwrap());
+        let daemon = daemon(table(&[10, 20, 21]), safety, EventBus::new());
+
+        let refused = daemon.handle(register(10, vec![21]));
+        assert!(refused.error.unwrap().starts_with("Refusing target 21:"));
+
+        let id = daemon.handle(register(10, vec![20])).id.unwrap();
+        let refused = daemon.handle(Request::AddTarget { id, pid: 21 });
+        assert!(refused.error.unwrap().starts_with("Refusing target 21:"));
+        assert_eq!(
+            daemon.handle(Request::List).contracts.unwrap()[0].targets,
+            vec![20]
+        );
+    }
+
+    #[test]
+    fn test_only_own_user_and_root_are_authorized() {
+        assert!(authorize(1000, 1000).is_ok());
+        assert!(authorize(0, 1000).is_ok());
+        assert_eq!(
+            authorize(1001, 1000),
+            Err("Refusing control connection from uid 1001: only uid 1000 and root may control this wick".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_socket_round_trip() {
+        let socket = std::env::temp_dir().join(format!("wick-test-{}.sock", std::process::id()));
+        let daemon = Arc::new(daemon(table(&[]), Safety::default(), EventBus::new()));
+        let cancel = CancellationToken::new();
+        let server = tokio::spawn(daemon.serve(socket.clone(), cancel.clone()));
+
+        let mut response = None;
+        for _ in 0..50 {
+            if let Ok(r) = send_request(&socket, &Request::List).await {
+                response = Some(r);
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(response.unwrap().contracts, Some(Vec::new()));
+
+        cancel.cancel();
+        server.await.unwrap().unwrap();
+        assert!(!socket.exists());
+    }
+
+    #[tokio::test]
+    async fn test_refuses_to_replace_a_regular_file() {
+        let path = std::env::temp_dir().join(format!("wick-test-{}.toml", std::process::id()));
+        std::fs::write(&path, "precious").unwrap();
+        let daemon = Arc::new(daemon(table(&[]), Safety::default(), EventBus::new()));
+
+        let result = daemon.serve(path.clone(), CancellationToken::new()).await;
+        assert!(result.is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "precious");
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_daemon_target_updates() {
+        let daemon = daemon(table(&[10, 20, 21]), Safety::default(), EventBus::new());
+        let id = daemon.handle(register(10, vec![20])).id.unwrap();
+
+        assert!(
+            daemon
+                .handle(Request::AddTarget {
+                    id: id.clone(),
+                    pid: 21
+                })
+                .ok
+        );
+        assert!(
+            daemon
+                .handle(Request::RemoveTarget {
+                    id: id.clone(),
+                    pid: 20
+                })
+                .ok
+        );
+        assert!(
+            !daemon
+                .handle(Request::RemoveTarget {
+                    id: id.clone(),
+                    pid: 20
+                })
+                .ok
+        );
+        assert!(
+            !daemon
+                .handle(Request::AddTarget {
+                    id: "nope".to_string(),
+                    pid: 1
+                })
+                .ok
+        );
+
+        let listed = daemon.handle(Request::List).contracts.unwrap();
+        assert_eq!(listed[0].targets, vec![21]);
+    }
+
+    #[test]
+    fn test_contract_control() {
+        let contract = Contract::new(10, vec![20], Vengeance::new(Duration::ZERO));
+        let control = ContractControl::new(&contract);
+        let add = |pid| Request::AddTarget {
+            id: WATCH_CONTRACT_ID.to_string(),
+            pid,
+        };
+
+        assert!(control.handle(add(21)).ok);
+        assert_eq!(contract.targets().pids(), vec![20, 21]);
+        assert!(
+            !control
+                .handle(Request::AddTarget {
+                    id: "c1".to_string(),
+                    pid: 22
+                })
+                .ok
+        );
+
+        contract.targets().seal();
+        assert!(!control.handle(add(23)).ok);
+        assert!(
+            !control
+                .handle(Request::Remove {
+                    id: WATCH_CONTRACT_ID.to_string()
+                })
+                .ok
+        );
+        assert!(!contract.cancellation_token().is_cancelled());
+    }
+}
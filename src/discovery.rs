@@ -0,0 +1,427 @@
+use log::info;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+
+use crate::process_table::{MemoryTable, ProcessTable};
+use crate::{build_process_tree_with, get_pids_by_depth};
+
+/// How a process was tied to a target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Discovery {
+    /// The target itself
+    Target,
+    /// Reached by following parent links down from the target
+    Descendant,
+    /// Shares the session the target had when the contract started
+    Session,
+    /// Shares the process group the target had when the contract started
+    ProcessGroup,
+    /// Was seen below the target on an earlier tick, then lost its parent
+    Orphan,
+}
+
+impl fmt::Display for Discovery {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Discovery::Target => write!(f, "target"),
+            Discovery::Descendant => write!(f, "child"),
+            Discovery::Session => write!(f, "sid"),
+            Discovery::ProcessGroup => write!(f, "pgid"),
+            Discovery::Orphan => write!(f, "orphan"),
+        }
+    }
+}
+
+/// A target's session and process group, recorded when its contract starts
+///
+/// Daemonized grandchildren that were reparented to init keep these IDs, so
+/// they can still be found once the parent links are gone.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Origin {
+    pub sid: Option<u32>,
+    pub pgid: Option<u32>,
+}
+
+impl Origin {
+    /// Records the session and process group of `pid`
+    ///
+    /// A session is only kept when `pid` created it with `setsid` (leads it);
+    /// any other session belongs to a login shell, terminal or supervisor the
+    /// target merely runs under. IDs shared with process-wick itself are
+    /// dropped too: sweeping them would take down wick's own session or group.
+    /// A process group is only kept when `pid` does not lead it, since the
+    /// group signal already reaches the members of a group the target leads.
+    pub fn of(table: &dyn ProcessTable, pid: u32) -> Option<Origin> {
+        let info = table.process(pid)?;
+        let (own_sid, own_pgid) = own_session_and_group();
+        let usable = |id: Option<u32>, own: Option<u32>| id.filter(|&id| id > 1 && Some(id) != own);
+        let origin = Origin {
+            sid: usable(info.sid, own_sid).filter(|&sid| sid == pid),
+            pgid: usable(info.pgid, own_pgid).filter(|&pgid| pgid != pid),
+        };
+        (origin != Origin::default()).then_some(origin)
+    }
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ids: Vec<String> = [("sid", self.sid), ("pgid", self.pgid)]
+            .into_iter()
+            .filter_map(|(name, id)| id.map(|id| format!("{} {}", name, id)))
+            .collect();
+        write!(f, "{}", ids.join(", "))
+    }
+}
+
+fn own_session_and_group() -> (Option<u32>, Option<u32>) {
+    #[cfg(unix)]
+    {
+        let sid = unsafe { libc::getsid(0) };
+        let pgid = unsafe { libc::getpgid(0) };
+        (
+            (sid >= 0).then_some(sid as u32),
+            (pgid >= 0).then_some(pgid as u32),
+        )
+    }
+
+    #[cfg(not(unix))]
+    {
+        (None, None)
+    }
+}
+
+/// Every process ever seen below each target, pinned by start time
+///
+/// Tracked on every tick, so grandchildren that are reparented to init before
+/// the dog dies are still known to belong to their target.
+#[derive(Debug, Default)]
+pub struct Lineage {
+    seen: Mutex<HashMap<u32, BTreeMap<u32, u64>>>,
+    /// Start time each target had when it was first tracked
+    started: Mutex<HashMap<u32, u64>>,
+    /// Forked children that exited before their start time could be read,
+    /// kept until their exit event so their own children are still attributed
+    transient: Mutex<HashMap<u32, u32>>,
+}
+
+impl Lineage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the current descendants of every target from one snapshot
+    ///
+    /// Processes that are gone, or whose PID now belongs to a process with a
+    /// different start time, are forgotten.
+    pub fn track(&self, table: &dyn ProcessTable, targets: &[u32]) {
+        let snapshot = MemoryTable::snapshot_of(table);
+        let mut started = self.started.lock().unwrap();
+        started.retain(|target, _| targets.contains(target));
+        for &target in targets {
+            if let Some(start_time) = snapshot.start_time(target) {
+                started.entry(target).or_insert(start_time);
+            }
+        }
+        drop(started);
+
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|target, _| targets.contains(target));
+        for &target in targets {
+            let members = seen.entry(target).or_default();
+            members.retain(|&pid, &mut start_time| snapshot.start_time(pid) == Some(start_time));
+            for pid in build_process_tree_with(&snapshot, target).into_keys() {
+                if pid == target {
+                    continue;
+                }
+                if let Some(start_time) = snapshot.start_time(pid) {
+                    if members.insert(pid, start_time).is_none() {
+                        info!("🧬 Tracking PID {} under target {}", pid, target);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Records a fork reported by the kernel, returning the child's target
+    ///
+    /// The child is kept when its parent is a target or already tracked under
+    /// one, so processes living shorter than a tick are seen too.
+    pub fn record_fork(
+        &self,
+        table: &dyn ProcessTable,
+        targets: &[u32],
+        parent: u32,
+        child: u32,
+    ) -> Option<u32> {
+        let mut seen = self.seen.lock().unwrap();
+        let mut transient = self.transient.lock().unwrap();
+        let target = targets
+            .iter()
+            .copied()
+            .find(|&target| {
+                target == parent || seen.get(&target).is_some_and(|m| m.contains_key(&parent))
+            })
+            .or_else(|| transient.get(&parent).copied())?;
+        match table.start_time(child) {
+            Some(start_time) => {
+                seen.entry(target).or_default().insert(child, start_time);
+                info!("🧬 Tracking PID {} under target {} (forked)", child, target);
+            }
+            None => {
+                transient.insert(child, target);
+            }
+        }
+        Some(target)
+    }
+
+    /// Forgets a process the kernel reported as exited
+    pub fn record_exit(&self, pid: u32) {
+        for members in self.seen.lock().unwrap().values_mut() {
+            members.remove(&pid);
+        }
+        self.transient.lock().unwrap().remove(&pid);
+    }
+
+    /// Every PID seen below `target`, with the start time it was seen with
+    pub fn members(&self, target: u32) -> BTreeMap<u32, u64> {
+        self.seen
+            .lock()
+            .unwrap()
+            .get(&target)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Every tracked target and descendant, with the start time it was first seen with
+    ///
+    /// A target keeps its first start time even if its PID is recycled later.
+    pub fn pinned(&self) -> BTreeMap<u32, u64> {
+        let mut pinned: BTreeMap<u32, u64> = self
+            .seen
+            .lock()
+            .unwrap()
+            .values()
+            .flatten()
+            .map(|(&pid, &start_time)| (pid, start_time))
+            .collect();
+        pinned.extend(self.started.lock().unwrap().iter());
+        pinned
+    }
+}
+
+/// Finds every process tied to `root_pid`, tagged with how it was found
+///
+/// The process tree comes first, shallowest first, followed in PID order by
+/// the processes sharing the recorded session or process group and by former
+/// descendants (`lineage`, PIDs pinned by start time) that lost their parent.
+/// Every other process of the recorded session is swept, unrelated jobs
+/// included; only session leaders and the target's ancestors are spared.
+/// Works on a single snapshot of `table`.
+pub fn discover_with(
+    table: &dyn ProcessTable,
+    root_pid: u32,
+    origin: Option<&Origin>,
+    lineage: &BTreeMap<u32, u64>,
+) -> Vec<(u32, Discovery)> {
+    let snapshot = MemoryTable::snapshot_of(table);
+    let tree = build_process_tree_with(&snapshot, root_pid);
+    let mut found: Vec<(u32, Discovery)> = get_pids_by_depth(&tree)
+        .into_iter()
+        .map(|pid| {
+            let how = if pid == root_pid {
+                Discovery::Target
+            } else {
+                Discovery::Descendant
+            };
+            (pid, how)
+        })
+        .collect();
+
+    let mut ancestors = HashSet::new();
+    let mut parent = snapshot.parent_pid(root_pid);
+    while let Some(pid) = parent.filter(|&pid| pid != 0 && ancestors.insert(pid)) {
+        parent = snapshot.parent_pid(pid);
+    }
+
+    let mut related = BTreeMap::new();
+    for process in snapshot.processes() {
+        if tree.contains_key(&process.pid) {
+            continue;
+        }
+        if lineage.get(&process.pid) == Some(&process.start_time) {
+            related.insert(process.pid, Discovery::Orphan);
+        } else if process.sid == Some(process.pid) || ancestors.contains(&process.pid) {
+            continue;
+        } else if let Some(origin) = origin {
+            if origin.sid.is_some() && process.sid == origin.sid {
+                related.insert(process.pid, Discovery::Session);
+            } else if origin.pgid.is_some() && process.pgid == origin.pgid {
+                related.insert(process.pid, Discovery::ProcessGroup);
+            }
+        }
+    }
+    found.extend(related);
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_table::ProcessInfo;
+
+    #[test]
+    fn test_discover_by_session_and_group() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(100, 1, 0).with_group(100, 100));
+        table.insert(ProcessInfo::new(101, 100, 0).with_group(100, 100));
+        // Double-forked daemon, reparented to init
+        table.insert(ProcessInfo::new(150, 1, 0).with_group(150, 100));
+        // Worker that joined the target's group from elsewhere
+        table.insert(ProcessInfo::new(160, 1, 0).with_group(90, 80));
+        table.insert(ProcessInfo::new(200, 1, 0).with_group(200, 200));
+
+        let origin = Origin {
+            sid: Some(100),
+            pgid: Some(90),
+        };
+        assert_eq!(
+            discover_with(&table, 100, Some(&origin), &BTreeMap::new()),
+            vec![
+                (100, Discovery::Target),
+                (101, Discovery::Descendant),
+                (150, Discovery::Session),
+                (160, Discovery::ProcessGroup),
+            ]
+        );
+        assert_eq!(
+            discover_with(&table, 100, None, &BTreeMap::new()),
+            vec![(100, Discovery::Target), (101, Discovery::Descendant)]
+        );
+    }
+
+    #[test]
+    fn test_sweep_takes_other_jobs_of_the_session_but_not_its_leader_or_ancestors() {
+        let table = MemoryTable::new();
+        // Shell leading the session, and the target's parent
+        table.insert(ProcessInfo::new(10, 1, 0).with_group(10, 10));
+        table.insert(ProcessInfo::new(20, 10, 0).with_group(20, 10));
+        table.insert(ProcessInfo::new(100, 20, 0).with_group(20, 10));
+        // Another job of the same terminal: it shares the session, so it is swept
+        table.insert(ProcessInfo::new(30, 10, 0).with_group(30, 10));
+
+        let origin = Origin {
+            sid: Some(10),
+            pgid: Some(20),
+        };
+        assert_eq!(
+            discover_with(&table, 100, Some(&origin), &BTreeMap::new()),
+            vec![(100, Discovery::Target), (30, Discovery::Session)]
+        );
+    }
+
+    #[test]
+    fn test_lineage_keeps_orphans_pinned_by_start_time() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(100, 1, 10));
+        table.insert(ProcessInfo::new(101, 100, 20));
+        table.insert(ProcessInfo::new(102, 101, 30));
+        let lineage = Lineage::new();
+        lineage.track(&table, &[100]);
+
+        // 101 exits; its child is reparented to init
+        table.remove(101);
+        table.insert(ProcessInfo::new(102, 1, 30));
+        lineage.track(&table, &[100]);
+        assert_eq!(lineage.members(100), BTreeMap::from([(102, 30)]));
+        assert_eq!(
+            discover_with(&table, 100, None, &lineage.members(100)),
+            vec![(100, Discovery::Target), (102, Discovery::Orphan)]
+        );
+
+        // The PID is reused by an unrelated process
+        table.insert(ProcessInfo::new(102, 1, 99));
+        assert_eq!(
+            discover_with(&table, 100, None, &lineage.members(100)),
+            vec![(100, Discovery::Target)]
+        );
+        lineage.track(&table, &[100]);
+        assert!(lineage.members(100).is_empty());
+    }
+
+    #[test]
+    fn test_pinned_targets_keep_their_first_start_time() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(100, 1, 10));
+        table.insert(ProcessInfo::new(101, 100, 20));
+        let lineage = Lineage::new();
+        lineage.track(&table, &[100]);
+        assert_eq!(lineage.pinned(), BTreeMap::from([(100, 10), (101, 20)]));
+
+        // The target exits and its PID is reused
+        table.remove(101);
+        table.insert(ProcessInfo::new(100, 1, 50));
+        lineage.track(&table, &[100]);
+        assert_eq!(lineage.pinned(), BTreeMap::from([(100, 10)]));
+    }
+
+    #[test]
+    fn test_lineage_follows_forks_and_exits() {
+        let table = MemoryTable::new();
+        table.spawn(100, 1);
+        table.insert(ProcessInfo::new(101, 100, 20));
+        table.insert(ProcessInfo::new(102, 1, 30));
+        let lineage = Lineage::new();
+
+        assert_eq!(lineage.record_fork(&table, &[100], 100, 101), Some(100));
+        // Forked by a tracked child, then reparented before any tick
+        assert_eq!(lineage.record_fork(&table, &[100], 101, 102), Some(100));
+        assert_eq!(lineage.record_fork(&table, &[100], 7, 8), None);
+        assert_eq!(lineage.members(100), BTreeMap::from([(101, 20), (102, 30)]));
+
+        lineage.record_exit(101);
+        assert_eq!(lineage.members(100), BTreeMap::from([(102, 30)]));
+
+        // A middle process already gone when its fork is handled
+        table.insert(ProcessInfo::new(104, 1, 40));
+        assert_eq!(lineage.record_fork(&table, &[100], 102, 103), Some(100));
+        assert_eq!(lineage.record_fork(&table, &[100], 103, 104), Some(100));
+        lineage.record_exit(103);
+        assert_eq!(lineage.members(100), BTreeMap::from([(102, 30), (104, 40)]));
+        assert_eq!(lineage.record_fork(&table, &[100], 103, 105), None);
+    }
+
+    #[test]
+    fn test_origin_skips_own_ids_and_led_group() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(100, 1, 0).with_group(100, 100));
+        table.insert(ProcessInfo::new(101, 100, 0).with_group(100, 100));
+
+        assert_eq!(
+            Origin::of(&table, 100),
+            Some(Origin {
+                sid: Some(100),
+                pgid: None,
+            })
+        );
+        // 101 only runs in a session someone else created
+        assert_eq!(
+            Origin::of(&table, 101),
+            Some(Origin {
+                sid: None,
+                pgid: Some(100),
+            })
+        );
+        assert_eq!(Origin::of(&table, 999), None);
+
+        #[cfg(unix)]
+        {
+            let me = std::process::id();
+            let (sid, pgid) = own_session_and_group();
+            table.insert(ProcessInfo::new(300, me, 0).with_group(pgid.unwrap(), sid.unwrap()));
+            assert_eq!(Origin::of(&table, 300), None);
+        }
+    }
+}
@@ -0,0 +1,213 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use tokio::sync::broadcast;
+
+use crate::discovery::Discovery;
+use crate::hooks::HookKind;
+use crate::limits::Usage;
+use crate::reaper::KillSignal;
+
+/// Number of events a slow subscriber may fall behind before it starts lagging
+const EVENT_CAPACITY: usize = 256;
+
+/// What set off a vengeance sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trigger {
+    /// The dog died
+    DogDeath,
+    /// The dog is still there but looks hung, so it counts as dead
+    DogHang,
+    /// The contract's deadline or maximum lifetime ran out
+    Deadline,
+    /// A target went over one of its budgets
+    ResourceLimit,
+    /// process-wick itself was told to stop and executes on its way out
+    Signal,
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trigger::DogDeath => write!(f, "dog death"),
+            Trigger::DogHang => write!(f, "dog hang"),
+            Trigger::Deadline => write!(f, "deadline"),
+            Trigger::ResourceLimit => write!(f, "resource limit"),
+            Trigger::Signal => write!(f, "signal"),
+        }
+    }
+}
+
+/// Something that happened while a contract was being watched or executed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WickEvent {
+    /// The watched process is gone
+    DogDied { pid: u32 },
+    /// A vengeance sequence is starting on `targets`
+    Triggered { trigger: Trigger, targets: Vec<u32> },
+    /// A target's tree went over one of its budgets and is being taken down
+    LimitExceeded {
+        target: u32,
+        limit: String,
+        usage: Usage,
+    },
+    /// A target was dropped because it must never be signalled
+    TargetRefused { pid: u32, reason: String },
+    /// A wave of targets is being taken down; earlier waves are confirmed dead
+    WaveStarted { wave: usize, pids: Vec<u32> },
+    /// A target's process tree was resolved for individual termination
+    ///
+    /// `how` tells how each PID was tied to the target.
+    TreeDiscovered {
+        target: u32,
+        pids: Vec<u32>,
+        how: BTreeMap<u32, Discovery>,
+    },
+    /// A signal was delivered to a process (or to its group)
+    SignalSent {
+        pid: u32,
+        signal: KillSignal,
+        group: bool,
+    },
+    /// A target's graceful-stop action finished; `error` is set if it failed
+    GracefulStopFinished { pid: u32, error: Option<String> },
+    /// A signal could not be delivered
+    SignalFailed {
+        pid: u32,
+        signal: KillSignal,
+        group: bool,
+        error: String,
+    },
+    /// A process that was being terminated is confirmed gone
+    ProcessExited { pid: u32 },
+    /// The vengeance delay ran out with these processes still alive
+    GraceExpired { survivors: Vec<u32> },
+    /// A hook command finished, failed or timed out
+    HookFinished {
+        hook: HookKind,
+        exit_code: Option<i32>,
+        timed_out: bool,
+    },
+    /// The contract is fulfilled
+    Retired,
+    /// The contract was called off before the dog died
+    Disarmed,
+}
+
+impl fmt::Display for WickEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WickEvent::DogDied { pid } => write!(f, "dog {} died", pid),
+            WickEvent::Triggered { trigger, targets } => {
+                write!(f, "{} triggered vengeance on {:?}", trigger, targets)
+            }
+            WickEvent::LimitExceeded {
+                target,
+                limit,
+                usage,
+            } => write!(f, "target {} exceeded {} ({})", target, limit, usage),
+            WickEvent::TargetRefused { pid, reason } => {
+                write!(f, "target {} refused: {}", pid, reason)
+            }
+            WickEvent::WaveStarted { wave, pids } => write!(f, "wave {} started: {:?}", wave, pids),
+            WickEvent::TreeDiscovered { target, pids, .. } => {
+                write!(f, "tree of {} discovered: {:?}", target, pids)
+            }
+            WickEvent::SignalSent { pid, signal, group } => {
+                let scope = if *group { "group of " } else { "" };
+                write!(f, "{} sent to {}{}", signal, scope, pid)
+            }
+            WickEvent::SignalFailed {
+                pid,
+                signal,
+                group,
+                error,
+            } => {
+                let scope = if *group { "group of " } else { "" };
+                write!(f, "{} to {}{} failed: {}", signal, scope, pid, error)
+            }
+            WickEvent::GracefulStopFinished { pid, error } => match error {
+                Some(error) => write!(f, "graceful stop of {} failed: {}", pid, error),
+                None => write!(f, "graceful stop of {} accepted", pid),
+            },
+            WickEvent::ProcessExited { pid } => write!(f, "{} exited", pid),
+            WickEvent::GraceExpired { survivors } => {
+                write!(f, "grace expired, survivors: {:?}", survivors)
+            }
+            WickEvent::HookFinished {
+                hook,
+                exit_code,
+                timed_out,
+            } => match (exit_code, timed_out) {
+                (_, true) => write!(f, "{} hook timed out", hook),
+                (Some(code), _) => write!(f, "{} hook exited with {}", hook, code),
+                (None, _) => write!(f, "{} hook did not exit normally", hook),
+            },
+            WickEvent::Retired => write!(f, "retired"),
+            WickEvent::Disarmed => write!(f, "disarmed"),
+        }
+    }
+}
+
+/// Broadcasts [`WickEvent`]s to any number of subscribers
+///
+/// Emitting never blocks and is a no-op when nobody is subscribed.
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<WickEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<WickEvent> {
+        self.sender.subscribe()
+    }
+
+    pub fn emit(&self, event: WickEvent) {
+        let _ = self.sender.send(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json_shape() {
+        let event = WickEvent::SignalSent {
+            pid: 42,
+            signal: KillSignal::Term,
+            group: true,
+        };
+        assert_eq!(
+            serde_json::to_string(&event).unwrap(),
+            r#"{"event":"signal_sent","pid":42,"signal":"SIGTERM","group":true}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&WickEvent::Retired).unwrap(),
+            r#"{"event":"retired"}"#
+        );
+    }
+
+    #[test]
+    fn test_emit_without_subscribers() {
+        let bus = EventBus::new();
+        bus.emit(WickEvent::Retired);
+
+        let mut rx = bus.subscribe();
+        bus.emit(WickEvent::DogDied { pid: 7 });
+        assert_eq!(rx.try_recv().unwrap(), WickEvent::DogDied { pid: 7 });
+    }
+}
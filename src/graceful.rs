@@ -0,0 +1,238 @@
+use log::{info, warn};
+use std::fmt;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::hooks::shell;
+
+/// How a target is asked to stop before it gets signals
+///
+/// Replaces the first rung of the signal ladder for that target; later rungs
+/// (SIGKILL by default) remain the fallback.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GracefulStop {
+    /// Shell command, e.g. `redis-cli shutdown`
+    Command(String),
+    /// POST to an admin endpoint on localhost
+    Http(LocalUrl),
+}
+
+/// An `http://` URL pointing at the local machine
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LocalUrl {
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl FromStr for LocalUrl {
+    type Err = String;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let rest = url
+            .strip_prefix("http://")
+            .ok_or_else(|| format!("Only http:// URLs are supported: {}", url))?;
+        let (authority, path) = match rest.find('/') {
+            Some(index) => (&rest[..index], &rest[index..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) if !authority.ends_with(']') => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("Invalid port in URL: {}", url))?,
+            ),
+            _ => (authority, 80),
+        };
+        if !matches!(host, "localhost" | "127.0.0.1" | "[::1]") {
+            return Err(format!(
+                "Graceful stop URL must point at localhost: {}",
+                url
+            ));
+        }
+        Ok(LocalUrl {
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+}
+
+impl fmt::Display for LocalUrl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "http://{}:{}{}", self.host, self.port, self.path)
+    }
+}
+
+impl FromStr for GracefulStop {
+    type Err = String;
+
+    /// Treats `http://...` as an HTTP POST and anything else as a shell command
+    fn from_str(action: &str) -> Result<Self, Self::Err> {
+        let action = action.trim();
+        if action.is_empty() {
+            return Err("Graceful stop action is empty".to_string());
+        }
+        if action.starts_with("http://") {
+            Ok(GracefulStop::Http(action.parse()?))
+        } else {
+            Ok(GracefulStop::Command(action.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for GracefulStop {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GracefulStop::Command(command) => write!(f, "{}", command),
+            GracefulStop::Http(url) => write!(f, "POST {}", url),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for GracefulStop {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let action = String::deserialize(deserializer)?;
+        action.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl GracefulStop {
+    /// Runs the action for the given target, giving up after `timeout`
+    ///
+    /// Commands see the target in `WICK_TARGET_PID`. Succeeds when the command
+    /// exits with status 0 or the endpoint answers with a 2xx status.
+    pub async fn run(&self, pid: u32, timeout: Duration) -> Result<(), String> {
+        info!("🛎️ Asking PID {} to stop: {}", pid, self);
+        let result = match tokio::time::timeout(timeout, self.run_inner(pid)).await {
+            Ok(result) => result,
+            Err(_) => Err(format!("timed out after {}s", timeout.as_secs())),
+        };
+        match &result {
+            Ok(()) => info!("✅ Graceful stop of PID {} accepted", pid),
+            Err(e) => warn!("❌ Graceful stop of PID {} failed: {}", pid, e),
+        }
+        result
+    }
+
+    async fn run_inner(&self, pid: u32) -> Result<(), String> {
+        match self {
+            GracefulStop::Command(command) => {
+                let output = shell(command)
+                    .env("WICK_TARGET_PID", pid.to_string())
+                    .stdin(Stdio::null())
+                    .kill_on_drop(true)
+                    .output()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                for line in String::from_utf8_lossy(&output.stdout)
+                    .lines()
+                    .chain(String::from_utf8_lossy(&output.stderr).lines())
+                {
+                    info!("🛎️ PID {} stop: {}", pid, line);
+                }
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(output.status.to_string())
+                }
+            }
+            GracefulStop::Http(url) => post(url).await,
+        }
+    }
+}
+
+/// Sends an empty HTTP/1.1 POST and checks the status line
+async fn post(url: &LocalUrl) -> Result<(), String> {
+    let host = url.host.trim_start_matches('[').trim_end_matches(']');
+    let mut stream = TcpStream::connect((host, url.port))
+        .await
+        .map_err(|e| e.to_string())?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        url.path, url.host, url.port
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .await
+        .map_err(|e| e.to_string())?;
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        Some(_) => Err(status_line.to_string()),
+        None => Err("no HTTP response".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_graceful_stop() {
+        assert_eq!(
+            "redis-cli shutdown".parse(),
+            Ok(GracefulStop::Command("redis-cli shutdown".to_string()))
+        );
+        assert_eq!(
+            "http://127.0.0.1:8080/admin/stop".parse(),
+            Ok(GracefulStop::Http(LocalUrl {
+                host: "127.0.0.1".to_string(),
+                port: 8080,
+                path: "/admin/stop".to_string(),
+            }))
+        );
+        assert_eq!(
+            "http://localhost"
+                .parse::<LocalUrl>()
+                .map(|url| url.to_string()),
+            Ok("http://localhost:80/".to_string())
+        );
+        assert!("http://example.com/stop".parse::<GracefulStop>().is_err());
+        assert!("http://localhost:notaport/"
+            .parse::<GracefulStop>()
+            .is_err());
+        assert!("".parse::<GracefulStop>().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_http_stop() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 1024];
+            let n = socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 204 No Content\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let stop: GracefulStop = format!("http://127.0.0.1:{}/shutdown", port)
+            .parse()
+            .unwrap();
+        assert_eq!(stop.run(42, Duration::from_secs(5)).await, Ok(()));
+        assert!(server.await.unwrap().starts_with("POST /shutdown HTTP/1.1"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_stop() {
+        let stop = GracefulStop::Command("test \"$WICK_TARGET_PID\" = 42".to_string());
+        assert_eq!(stop.run(42, Duration::from_secs(5)).await, Ok(()));
+        assert!(stop.run(43, Duration::from_secs(5)).await.is_err());
+    }
+}
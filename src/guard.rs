@@ -0,0 +1,295 @@
+use log::{info, warn};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::process_table::{native_table, ProcessTable};
+use crate::reaper::{KillSignal, SignalReaper};
+use crate::safety::Safety;
+use crate::{
+    build_process_tree_with, get_pids_by_depth, kill_process_group_with, send_signal_to_pids_with,
+};
+
+/// How often the guard checks whether its targets are gone during the grace period
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Terminates the registered processes when dropped
+///
+/// Covers the clean-shutdown path of an embedding application, including panic
+/// unwinds; the external `process-wick` binary covers crashes. The drop runs the
+/// same graceful→force sequence synchronously, so it blocks for at most the
+/// vengeance delay plus the time it takes to deliver the signals.
+///
+/// ```no_run
+/// let child = std::process::Command::new("my-sidecar").spawn().unwrap();
+/// let _guard = process_wick::Guard::new([child.id()]);
+/// // ... when `_guard` goes out of scope, the sidecar and its children die
+/// ```
+pub struct Guard {
+    pids: Vec<u32>,
+    vengeance_delay: Duration,
+    armed: bool,
+    table: Arc<dyn ProcessTable>,
+    safety: Safety,
+}
+
+impl std::fmt::Debug for Guard {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Guard")
+            .field("pids", &self.pids)
+            .field("vengeance_delay", &self.vengeance_delay)
+            .field("armed", &self.armed)
+            .field("safety", &self.safety)
+            .finish()
+    }
+}
+
+impl Guard {
+    pub fn new(children: impl IntoIterator<Item = u32>) -> Self {
+        let mut guard = Self {
+            pids: Vec::new(),
+            vengeance_delay: Duration::from_secs(5),
+            armed: true,
+            table: native_table(),
+            safety: Safety::default(),
+        };
+        for pid in children {
+            guard.add(pid);
+        }
+        guard
+    }
+
+    /// Sets how long to wait after SIGTERM before force-killing
+    pub fn with_vengeance_delay(mut self, delay: Duration) -> Self {
+        self.vengeance_delay = delay;
+        self
+    }
+
+    /// Never signals processes matched by the given deny list
+    ///
+    /// PID 0, PID 1, the current process and its ancestors are always refused.
+    pub fn with_safety(mut self, safety: Safety) -> Self {
+        self.safety = safety;
+        self
+    }
+
+    /// Registers another process to terminate on drop
+    pub fn add(&mut self, pid: u32) {
+        if !self.pids.contains(&pid) {
+            self.pids.push(pid);
+        }
+    }
+
+    pub fn pids(&self) -> &[u32] {
+        &self.pids
+    }
+
+    /// Lets the registered processes live on after the guard is dropped
+    pub fn disarm(&mut self) {
+        self.armed = false;
+    }
+
+    fn execute(&self) {
+        info!("🛡️ Guard dropped, terminating {:?}", self.pids);
+        let mut survivors = self.terminate(&self.pids, false);
+
+        // Wait for graceful termination, but no longer than necessary
+        let deadline = Instant::now() + self.vengeance_delay;
+        while Instant::now() < deadline {
+            survivors.retain(|&pid| self.lingers(pid));
+            if survivors.is_empty() {
+                break;
+            }
+            std::thread::sleep(EXIT_POLL_INTERVAL);
+        }
+
+        survivors.retain(|&pid| self.lingers(pid));
+        if survivors.is_empty() {
+            return;
+        }
+
+        // A group is only signalled through its leader while the leader lives;
+        // the PIDs of the others may already belong to someone else
+        info!("🔪 Guard force killing what is left: {:?}", survivors);
+        let leaders: Vec<u32> = self
+            .pids
+            .iter()
+            .copied()
+            .filter(|pid| survivors.contains(pid))
+            .collect();
+        for leader in leaders {
+            if self.signal_group(leader, KillSignal::Kill) {
+                survivors.retain(|&pid| pid != leader);
+            }
+        }
+        self.signal_pids(&survivors, KillSignal::Kill);
+    }
+
+    /// Whether `pid` still runs; the registered processes are usually the
+    /// application's own children, which stay zombies until it reaps them
+    fn lingers(&self, pid: u32) -> bool {
+        self.table
+            .process(pid)
+            .is_some_and(|p| p.state != Some('Z'))
+    }
+
+    /// Signals the group led by `pid`, unless it would reach a refused process
+    fn signal_group(&self, pid: u32, sig: KillSignal) -> bool {
+        match self
+            .safety
+            .check_group(self.table.as_ref(), &SignalReaper, pid)
+        {
+            Ok(()) => kill_process_group_with(&SignalReaper, pid, sig),
+            Err(reason) => {
+                warn!(
+                    "🛡️ Refusing to send {} to process group of PID {}: {}",
+                    sig, pid, reason
+                );
+                false
+            }
+        }
+    }
+
+    fn signal_pids(&self, pids: &[u32], sig: KillSignal) {
+        let allowed: Vec<u32> = pids
+            .iter()
+            .copied()
+            .filter(|&pid| match self.safety.check(self.table.as_ref(), pid) {
+                Ok(()) => true,
+                Err(reason) => {
+                    warn!("🛡️ Refusing to send {} to PID {}: {}", sig, pid, reason);
+                    false
+                }
+            })
+            .collect();
+        send_signal_to_pids_with(self.table.as_ref(), &SignalReaper, &allowed, sig);
+    }
+
+    /// Signals each target's group, falling back to its tree, and returns every
+    /// PID that was signalled individually
+    fn terminate(&self, targets: &[u32], force: bool) -> Vec<u32> {
+        let sig = KillSignal::from_force(force);
+        let mut signalled: Vec<u32> = Vec::new();
+
+        for &pid in targets {
+            if self.signal_group(pid, sig) {
+                signalled.push(pid);
+                continue;
+            }
+
+            let process_tree = build_process_tree_with(self.table.as_ref(), pid);
+            let pids_in_order = get_pids_by_depth(&process_tree);
+            self.signal_pids(&pids_in_order, sig);
+
+            for tree_pid in pids_in_order {
+                if !signalled.contains(&tree_pid) {
+                    signalled.push(tree_pid);
+                }
+            }
+        }
+
+        signalled
+    }
+}
+
+impl Drop for Guard {
+    fn drop(&mut self) {
+        if self.armed && !self.pids.is_empty() {
+            self.execute();
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::process::{Child, Command};
+
+    fn spawn_sleep() -> Child {
+        Command::new("sleep")
+            .arg("100")
+            .spawn()
+            .expect("Failed to spawn sleep")
+    }
+
+    #[test]
+    fn test_drop_terminates_children() {
+        let mut child = spawn_sleep();
+
+        drop(Guard::new([child.id()]).with_vengeance_delay(Duration::from_millis(200)));
+
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn test_panic_unwind_terminates_children() {
+        let mut child = spawn_sleep();
+        let pid = child.id();
+
+        let result = std::panic::catch_unwind(move || {
+            let _guard = Guard::new([pid]).with_vengeance_delay(Duration::from_millis(200));
+            panic!("app crashed");
+        });
+
+        assert!(result.is_err());
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn test_unreaped_children_do_not_hold_the_drop() {
+        let mut child = spawn_sleep();
+
+        // The child dies on SIGTERM but stays a zombie until we wait for it
+        let started = Instant::now();
+        drop(Guard::new([child.id()]).with_vengeance_delay(Duration::from_secs(3)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn test_protected_children_are_spared() {
+        use crate::safety::ProtectRule;
+
+        let mut child = spawn_sleep();
+
+        drop(
+            Guard::new([child.id()])
+                .with_vengeance_delay(Duration::from_millis(200))
+                .with_safety(Safety::new(vec![ProtectRule::Pid(child.id())])),
+        );
+
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+
+    #[test]
+    fn test_force_kills_survivors_of_the_grace_period() {
+        let mut child = Command::new("sh")
+            .args(["-c", "trap '' TERM; while true; do sleep 1; done"])
+            .spawn()
+            .expect("Failed to spawn sh");
+        std::thread::sleep(Duration::from_millis(100));
+
+        drop(Guard::new([child.id()]).with_vengeance_delay(Duration::from_millis(200)));
+
+        let status = child.wait().unwrap();
+        assert!(!status.success());
+    }
+
+    #[test]
+    fn test_disarmed_guard_leaves_children() {
+        let mut child = spawn_sleep();
+
+        let mut guard = Guard::new([child.id()]);
+        guard.disarm();
+        drop(guard);
+
+        assert!(child.try_wait().unwrap().is_none());
+        child.kill().unwrap();
+        child.wait().unwrap();
+    }
+}
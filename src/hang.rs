@@ -0,0 +1,239 @@
+use log::info;
+use serde::Deserialize;
+use std::fmt;
+use std::time::Duration;
+
+use crate::limits::UsageProbe;
+use crate::process_table::ProcessTable;
+
+/// Default number of ticks the dog may spend stuck before it counts as dead
+pub const DEFAULT_STUCK_TICKS: u32 = 5;
+
+/// Default number of ticks the dog may leave an exited child unreaped without
+/// using any CPU before it counts as dead
+pub const DEFAULT_IDLE_TICKS: u32 = 10;
+
+/// Thresholds of the opt-in heuristic that counts a hung dog as dead
+///
+/// Written in the config file as `hang = { stuck_ticks = 5, idle_ticks = 10 }`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HangDetection {
+    /// Consecutive ticks in uninterruptible sleep (`D`) or stopped (`T`, `t`)
+    pub stuck_ticks: u32,
+    /// Consecutive ticks without CPU progress while an exited child waits to be reaped
+    pub idle_ticks: u32,
+}
+
+impl Default for HangDetection {
+    fn default() -> Self {
+        Self {
+            stuck_ticks: DEFAULT_STUCK_TICKS,
+            idle_ticks: DEFAULT_IDLE_TICKS,
+        }
+    }
+}
+
+impl HangDetection {
+    /// Rejects zero thresholds, which would count a healthy dog as hung on the first tick
+    pub fn validate(&self) -> Result<(), String> {
+        if self.stuck_ticks == 0 {
+            return Err("hang.stuck_ticks must be at least 1".to_string());
+        }
+        if self.idle_ticks == 0 {
+            return Err("hang.idle_ticks must be at least 1".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Parses a hang threshold, which must be at least one tick
+pub fn parse_ticks(ticks: &str) -> Result<u32, String> {
+    match ticks.trim().parse() {
+        Ok(0) => Err("must be at least 1 tick".to_string()),
+        Ok(ticks) => Ok(ticks),
+        Err(_) => Err(format!("Invalid number of ticks: {}", ticks.trim())),
+    }
+}
+
+/// What one check saw of the dog
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DogSample {
+    pub state: Option<char>,
+    pub cpu_time: Option<Duration>,
+    /// Children that exited but were not reaped (zombies)
+    pub exited_children: usize,
+}
+
+impl DogSample {
+    /// Samples `pid` from the process table and the probe
+    pub fn take(table: &dyn ProcessTable, probe: &dyn UsageProbe, pid: u32) -> Self {
+        let exited_children = table
+            .processes()
+            .iter()
+            .filter(|p| p.parent_pid == pid && p.state == Some('Z'))
+            .count();
+        Self {
+            state: table.process(pid).and_then(|p| p.state),
+            cpu_time: probe.cpu_time(pid),
+            exited_children,
+        }
+    }
+}
+
+impl fmt::Display for DogSample {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "state {}", self.state.unwrap_or('?'))?;
+        if let Some(cpu_time) = self.cpu_time {
+            write!(f, ", CPU {}ms", cpu_time.as_millis())?;
+        }
+        write!(f, ", {} exited children unreaped", self.exited_children)
+    }
+}
+
+/// Counts consecutive suspicious checks of the dog
+#[derive(Debug)]
+pub struct HangDetector {
+    pid: u32,
+    thresholds: HangDetection,
+    stuck: u32,
+    idle: u32,
+    last_cpu_time: Option<Duration>,
+}
+
+impl HangDetector {
+    pub fn new(pid: u32, thresholds: HangDetection) -> Self {
+        Self {
+            pid,
+            thresholds,
+            stuck: 0,
+            idle: 0,
+            last_cpu_time: None,
+        }
+    }
+
+    /// Records one check, returning why the dog counts as hung, if it does
+    ///
+    /// Every check is logged, along with both counters.
+    pub fn check(&mut self, sample: DogSample) -> Option<String> {
+        let stuck = matches!(sample.state, Some('D' | 'T' | 't'));
+        self.stuck = if stuck { self.stuck + 1 } else { 0 };
+
+        // Without two CPU readings there is no progress to compare
+        let progress = match (self.last_cpu_time, sample.cpu_time) {
+            (Some(last), Some(now)) => Some(now.saturating_sub(last)),
+            _ => None,
+        };
+        self.last_cpu_time = sample.cpu_time;
+        let idle = sample.exited_children > 0 && progress == Some(Duration::ZERO);
+        self.idle = if idle { self.idle + 1 } else { 0 };
+
+        info!(
+            "🩺 Dog PID {}: {}; stuck {}/{}, idle {}/{}",
+            self.pid,
+            sample,
+            self.stuck,
+            self.thresholds.stuck_ticks,
+            self.idle,
+            self.thresholds.idle_ticks
+        );
+        if self.stuck >= self.thresholds.stuck_ticks {
+            return Some(format!(
+                "state {} for {} ticks",
+                sample.state.unwrap_or('?'),
+                self.stuck
+            ));
+        }
+        if self.idle >= self.thresholds.idle_ticks {
+            return Some(format!(
+                "no CPU progress for {} ticks with {} exited children unreaped",
+                self.idle, sample.exited_children
+            ));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::limits::MemoryProbe;
+    use crate::process_table::{MemoryTable, ProcessInfo};
+
+    #[test]
+    fn test_stuck_and_idle_dogs_count_as_hung() {
+        let thresholds = HangDetection {
+            stuck_ticks: 2,
+            idle_ticks: 3,
+        };
+        let sample = |state, cpu_ms, exited_children| DogSample {
+            state: Some(state),
+            cpu_time: Some(Duration::from_millis(cpu_ms)),
+            exited_children,
+        };
+
+        let mut detector = HangDetector::new(50, thresholds);
+        assert_eq!(detector.check(sample('D', 10, 0)), None);
+        // Waking up resets the count
+        assert_eq!(detector.check(sample('S', 10, 0)), None);
+        assert_eq!(detector.check(sample('T', 10, 0)), None);
+        assert_eq!(
+            detector.check(sample('D', 10, 0)),
+            Some("state D for 2 ticks".to_string())
+        );
+
+        let mut detector = HangDetector::new(50, thresholds);
+        assert_eq!(detector.check(sample('S', 10, 1)), None);
+        assert_eq!(detector.check(sample('S', 10, 1)), None);
+        assert_eq!(detector.check(sample('S', 10, 1)), None);
+        // Busy again: not hung, only slow to reap
+        assert_eq!(detector.check(sample('R', 20, 1)), None);
+        // Idle but with nothing to reap
+        assert_eq!(detector.check(sample('S', 20, 0)), None);
+        assert_eq!(detector.check(sample('S', 20, 1)), None);
+        assert_eq!(detector.check(sample('S', 20, 1)), None);
+        assert_eq!(
+            detector.check(sample('S', 20, 1)),
+            Some("no CPU progress for 3 ticks with 1 exited children unreaped".to_string())
+        );
+    }
+
+    #[test]
+    fn test_zero_thresholds_are_rejected() {
+        assert_eq!(HangDetection::default().validate(), Ok(()));
+        let zero_stuck = HangDetection {
+            stuck_ticks: 0,
+            ..HangDetection::default()
+        };
+        assert!(zero_stuck.validate().is_err());
+        let zero_idle = HangDetection {
+            idle_ticks: 0,
+            ..HangDetection::default()
+        };
+        assert!(zero_idle.validate().is_err());
+
+        assert_eq!(parse_ticks("3"), Ok(3));
+        assert!(parse_ticks("0").is_err());
+        assert!(parse_ticks("-1").is_err());
+    }
+
+    #[test]
+    fn test_sample_counts_zombie_children() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(50, 1, 0).with_state('S'));
+        table.insert(ProcessInfo::new(51, 50, 0).with_state('Z'));
+        table.insert(ProcessInfo::new(52, 50, 0).with_state('S'));
+        table.insert(ProcessInfo::new(60, 1, 0).with_state('Z'));
+        let probe = MemoryProbe::new();
+        probe.set_cpu_time(50, Duration::from_millis(7));
+
+        assert_eq!(
+            DogSample::take(&table, &probe, 50),
+            DogSample {
+                state: Some('S'),
+                cpu_time: Some(Duration::from_millis(7)),
+                exited_children: 1,
+            }
+        );
+    }
+}
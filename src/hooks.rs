@@ -0,0 +1,204 @@
+use log::{info, warn};
+use serde::Serialize;
+use std::fmt;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// Default time in seconds a hook may run before it is killed
+pub const DEFAULT_HOOK_TIMEOUT: u64 = 30;
+
+/// Point in the vengeance sequence where a hook runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookKind {
+    /// Right after the dog is found dead
+    OnDogDeath,
+    /// Right before the first signal is sent
+    BeforeKill,
+    /// Once every target is gone
+    AfterKill,
+}
+
+impl fmt::Display for HookKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HookKind::OnDogDeath => write!(f, "on-dog-death"),
+            HookKind::BeforeKill => write!(f, "before-kill"),
+            HookKind::AfterKill => write!(f, "after-kill"),
+        }
+    }
+}
+
+/// A shell command run at some point of the vengeance sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    pub command: String,
+    pub timeout: Duration,
+}
+
+/// What happened when a hook ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookOutcome {
+    /// Exit code, `None` if the hook timed out, was killed by a signal or could not start
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+}
+
+impl Hook {
+    pub fn new(command: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            command: command.into(),
+            timeout,
+        }
+    }
+
+    /// Runs the command through the shell, logging its exit status and output
+    ///
+    /// The hook sees `WICK_HOOK`, `WICK_DOG_PID` and `WICK_TARGETS` (comma-separated)
+    /// in its environment. A hook that outlives its timeout is killed.
+    pub async fn run(&self, kind: HookKind, dog_pid: u32, targets: &[u32]) -> HookOutcome {
+        let targets = targets
+            .iter()
+            .map(|pid| pid.to_string())
+            .collect::<Vec<_>>()
+            .join(",");
+
+        let mut command = shell(&self.command);
+        command
+            .env("WICK_HOOK", kind.to_string())
+            .env("WICK_DOG_PID", dog_pid.to_string())
+            .env("WICK_TARGETS", targets)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        info!("🪝 Running {} hook: {}", kind, self.command);
+        let child = match command.spawn() {
+            Ok(child) => child,
+            Err(e) => {
+                warn!("❌ Failed to start {} hook: {}", kind, e);
+                return HookOutcome {
+                    exit_code: None,
+                    timed_out: false,
+                };
+            }
+        };
+
+        match tokio::time::timeout(self.timeout, child.wait_with_output()).await {
+            Ok(Ok(output)) => {
+                log_output(kind, "stdout", &output.stdout);
+                log_output(kind, "stderr", &output.stderr);
+                if output.status.success() {
+                    info!("✅ {} hook finished: {}", kind, output.status);
+                } else {
+                    warn!("❌ {} hook failed: {}", kind, output.status);
+                }
+                HookOutcome {
+                    exit_code: output.status.code(),
+                    timed_out: false,
+                }
+            }
+            Ok(Err(e)) => {
+                warn!("❌ Failed to wait for {} hook: {}", kind, e);
+                HookOutcome {
+                    exit_code: None,
+                    timed_out: false,
+                }
+            }
+            Err(_) => {
+                // Dropping the future kills the child
+                warn!(
+                    "⏰ {} hook timed out after {}s and was killed",
+                    kind,
+                    self.timeout.as_secs()
+                );
+                HookOutcome {
+                    exit_code: None,
+                    timed_out: true,
+                }
+            }
+        }
+    }
+}
+
+/// The hooks attached to a contract
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Hooks {
+    pub on_dog_death: Option<Hook>,
+    pub before_kill: Option<Hook>,
+    pub after_kill: Option<Hook>,
+}
+
+impl Hooks {
+    pub fn get(&self, kind: HookKind) -> Option<&Hook> {
+        match kind {
+            HookKind::OnDogDeath => self.on_dog_death.as_ref(),
+            HookKind::BeforeKill => self.before_kill.as_ref(),
+            HookKind::AfterKill => self.after_kill.as_ref(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.on_dog_death.is_none() && self.before_kill.is_none() && self.after_kill.is_none()
+    }
+}
+
+#[cfg(unix)]
+pub(crate) fn shell(command: &str) -> Command {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    shell
+}
+
+#[cfg(windows)]
+pub(crate) fn shell(command: &str) -> Command {
+    let mut shell = Command::new("cmd");
+    shell.arg("/C").arg(command);
+    shell
+}
+
+fn log_output(kind: HookKind, stream: &str, output: &[u8]) {
+    for line in String::from_utf8_lossy(output).lines() {
+        info!("🪝 {} {}: {}", kind, stream, line);
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hook_sees_environment() {
+        let dir = std::env::temp_dir().join(format!("wick-hook-{}", std::process::id()));
+        let hook = Hook::new(
+            format!(
+                "echo \"$WICK_HOOK $WICK_DOG_PID $WICK_TARGETS\" > {}",
+                dir.display()
+            ),
+            Duration::from_secs(5),
+        );
+
+        let outcome = hook.run(HookKind::BeforeKill, 42, &[100, 101]).await;
+        assert_eq!(outcome.exit_code, Some(0));
+        assert_eq!(
+            std::fs::read_to_string(&dir).unwrap().trim(),
+            "before-kill 42 100,101"
+        );
+        let _ = std::fs::remove_file(dir);
+    }
+
+    #[tokio::test]
+    async fn test_hook_timeout_and_failure() {
+        let slow = Hook::new("sleep 10", Duration::from_millis(100));
+        let outcome = slow.run(HookKind::AfterKill, 1, &[]).await;
+        assert!(outcome.timed_out);
+        assert_eq!(outcome.exit_code, None);
+
+        let failing = Hook::new("exit 3", Duration::from_secs(5));
+        let outcome = failing.run(HookKind::OnDogDeath, 1, &[]).await;
+        assert!(!outcome.timed_out);
+        assert_eq!(outcome.exit_code, Some(3));
+    }
+}
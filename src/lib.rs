@@ -1,11 +1,53 @@
 use log::{info, warn};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::time::Duration;
+
+#[cfg(windows)]
 use sysinfo::{Pid, System};
 
+pub mod config;
+pub mod contract;
 #[cfg(unix)]
-use nix::{
-    sys::signal::{kill, Signal},
-    unistd::Pid as NixPid,
+pub mod daemon;
+pub mod discovery;
+pub mod events;
+pub mod graceful;
+pub mod guard;
+pub mod hang;
+pub mod hooks;
+pub mod limits;
+pub mod namespace;
+pub mod proc_events;
+pub mod process_table;
+pub mod reaper;
+pub mod safety;
+pub mod tree;
+pub mod vengeance;
+
+pub use config::{Config, ContractConfig, HookConfig, Selector};
+pub use contract::{Contract, ContractOutcome, SelfSignalPolicy, TargetSet};
+pub use discovery::{discover_with, Discovery, Lineage, Origin};
+pub use events::{EventBus, Trigger, WickEvent};
+pub use graceful::GracefulStop;
+pub use guard::Guard;
+pub use hang::HangDetection;
+pub use hooks::{Hook, HookKind, Hooks};
+pub use limits::{ByteSize, CpuLimit, ResourceLimits, Usage, UsageProbe};
+pub use namespace::{NamespacePolicy, NamespacedPid, PidNamespaces};
+pub use proc_events::{ProcConnector, ProcEvent};
+#[cfg(target_os = "linux")]
+pub use process_table::ProcfsTable;
+pub use process_table::{native_table, MemoryTable, ProcessInfo, ProcessTable, SysinfoTable};
+#[cfg(target_os = "linux")]
+pub use reaper::{CgroupReaper, PidfdReaper};
+pub use reaper::{KillSignal, ReapCall, Reaper, RecordingReaper, SignalReaper};
+pub use safety::{parse_protect_rules, ProtectRule, Safety};
+pub use tokio_util::sync::CancellationToken;
+pub use tree::{render_tree, tree_report, TreeEntry, TreeReport};
+pub use vengeance::{
+    customize_ladder, default_ladder, describe_ladder, shutdown_waves, PlannedAction, PlannedStep,
+    Rung, Vengeance,
 };
 
 // Process tree node structure
@@ -28,52 +70,67 @@ impl ProcessNode {
     }
 }
 
-/// Gets all processes with their parent PIDs using sysinfo (cross-platform)
+/// Gets all processes with their parent PIDs from the native process table
 pub fn get_all_processes() -> Vec<(u32, u32)> {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-
-    let mut processes = Vec::new();
-    for (pid, process) in sys.processes() {
-        let parent_pid = process.parent().unwrap_or(Pid::from(0));
-        processes.push((pid.as_u32(), parent_pid.as_u32()));
-    }
-
-    processes
+    native_table()
+        .processes()
+        .into_iter()
+        .map(|p| (p.pid, p.parent_pid))
+        .collect()
 }
 
-/// Builds a process tree s ting from the given root PID
+/// Builds a process tree starting from the given root PID
 pub fn build_process_tree(root_pid: u32) -> HashMap<u32, ProcessNode> {
+    build_process_tree_with(native_table().as_ref(), root_pid)
+}
+
+/// Builds a process tree starting from the given root PID using a single
+/// snapshot of the given process table
+pub fn build_process_tree_with(
+    table: &dyn ProcessTable,
+    root_pid: u32,
+) -> HashMap<u32, ProcessNode> {
     let mut process_tree: HashMap<u32, ProcessNode> = HashMap::new();
     let mut to_visit: VecDeque<u32> = VecDeque::new();
     let mut visited_pids: HashSet<u32> = HashSet::new();
 
+    // Index the snapshot by parent so every level is a cheap lookup
+    let mut children_by_parent: HashMap<u32, Vec<u32>> = HashMap::new();
+    for process in table.processes() {
+        if process.pid != process.parent_pid {
+            children_by_parent
+                .entry(process.parent_pid)
+                .or_default()
+                .push(process.pid);
+        }
+    }
+    for children in children_by_parent.values_mut() {
+        children.sort_unstable();
+    }
+
     // Start with the root process
     to_visit.push_back(root_pid);
     process_tree.insert(root_pid, ProcessNode::new(root_pid, 0));
 
     while let Some(current_pid) = to_visit.pop_front() {
-        if visited_pids.contains(&current_pid) {
+        if !visited_pids.insert(current_pid) {
             continue; // Skip cycles
         }
-        visited_pids.insert(current_pid);
 
         let current_depth = process_tree[&current_pid].depth;
-        let all_processes = get_all_processes();
-
-        for (pid, parent_pid) in all_processes {
-            if parent_pid == current_pid && pid != current_pid {
-                if !process_tree.contains_key(&pid) {
-                    let mut child_node = ProcessNode::new(pid, parent_pid);
-                    child_node.depth = current_depth + 1;
-                    process_tree.insert(pid, child_node);
-                    to_visit.push_back(pid);
-                }
+        let children = children_by_parent.get(&current_pid).into_iter().flatten();
+
+        for &pid in children {
+            if let Entry::Vacant(entry) = process_tree.entry(pid) {
+                let mut child_node = ProcessNode::new(pid, current_pid);
+                child_node.depth = current_depth + 1;
+                entry.insert(child_node);
+                to_visit.push_back(pid);
+            }
 
-                // Add this child to the current node's children list
-                if let Some(current_node) = process_tree.get_mut(&current_pid) {
-                    current_node.children.push(pid);
-                }
+            // Add this child to the current node's children list
+            if let Some(current_node) = process_tree.get_mut(&current_pid) {
+                current_node.children.push(pid);
             }
         }
     }
@@ -140,140 +197,113 @@ pub fn get_dog_pid(dog_arg: Option<u32>) -> u32 {
 }
 
 pub fn is_process_alive(pid: u32) -> bool {
-    let mut sys = System::new_all();
-    sys.refresh_all();
-    sys.process(Pid::from(pid as usize)).is_some()
+    native_table().is_alive(pid)
 }
 
-/// Gets all PIDs in the process tree sorted by depth (shallowest first)
+/// Gets all PIDs in the process tree sorted by depth (shallowest first), then by PID
 pub fn get_pids_by_depth(process_tree: &HashMap<u32, ProcessNode>) -> Vec<u32> {
     let mut all_pids: Vec<u32> = process_tree.keys().cloned().collect();
-    all_pids.sort_by_key(|&pid| process_tree.get(&pid).map(|n| n.depth).unwrap_or(0));
+    all_pids.sort_by_key(|&pid| {
+        let depth = process_tree.get(&pid).map(|n| n.depth).unwrap_or(0);
+        (depth, pid)
+    });
     all_pids
 }
 
 /// Sends signal to a list of PIDs in the specified order
 pub fn send_signal_to_pids(pids: &[u32], force: bool) {
-    for &pid in pids {
-        if is_process_alive(pid) {
-            #[cfg(unix)]
-            {
-                let sig = if force {
-                    Signal::SIGKILL
-                } else {
-                    Signal::SIGTERM
-                };
-
-                info!("Sending {} to PID {}", sig, pid);
-
-                match kill(NixPid::from_raw(pid as i32), sig) {
-                    Ok(_) => {
-                        info!("Successfully sent {} to PID {}.", sig, pid);
-                    }
-                    Err(e) => {
-                        warn!("Failed to send {} to PID {}: {:?}.", sig, pid, e);
-                    }
-                }
-            }
+    send_signal_to_pids_with(
+        native_table().as_ref(),
+        &SignalReaper,
+        pids,
+        KillSignal::from_force(force),
+    );
+}
 
-            #[cfg(windows)]
-            {
-                let sig_name = if force { "SIGKILL" } else { "SIGTERM" };
-                info!("Sending {} to PID {}", sig_name, pid);
+/// Sends signal to a list of PIDs in the specified order through the given reaper,
+/// skipping PIDs the process table reports as already gone
+///
+/// Returns the delivery result for every PID that was signalled.
+pub fn send_signal_to_pids_with(
+    table: &dyn ProcessTable,
+    reaper: &dyn Reaper,
+    pids: &[u32],
+    sig: KillSignal,
+) -> Vec<(u32, Result<(), String>)> {
+    let mut results = Vec::new();
 
-                let mut cmd = std::process::Command::new("taskkill");
-                cmd.arg("/PID").arg(&pid.to_string());
+    for &pid in pids {
+        if let Some(reason) = safety::hard_refusal(table, pid) {
+            warn!("🛡️ Refusing to send {} to PID {}: {}", sig, pid, reason);
+            results.push((pid, Err(format!("Refused: {}", reason))));
+            continue;
+        }
+        if table.is_alive(pid) {
+            info!("Sending {} to PID {}", sig, pid);
 
-                if force {
-                    cmd.arg("/F"); // Force kill
+            let result = reaper.signal(pid, sig);
+            match &result {
+                Ok(_) => {
+                    info!("Successfully sent {} to PID {}.", sig, pid);
                 }
-
-                match cmd.output() {
-                    Ok(output) => {
-                        if output.status.success() {
-                            info!("Successfully terminated PID {}.", pid);
-                        } else {
-                            warn!(
-                                "Failed to terminate PID {}. Status: {}.",
-                                pid, output.status
-                            );
-                        }
-                    }
-                    Err(e) => {
-                        warn!("Failed to execute taskkill for PID {}: {:?}", pid, e);
-                    }
+                Err(e) => {
+                    warn!("Failed to send {} to PID {}: {}.", sig, pid, e);
                 }
             }
+            results.push((pid, result));
         }
     }
+
+    results
 }
 
 /// Attempts to kill a process group, returns true if successful, false if it needs fallback
 pub fn kill_process_group(root_pid: u32, force: bool) -> bool {
-    #[cfg(unix)]
-    {
-        let sig = if force {
-            Signal::SIGKILL
-        } else {
-            Signal::SIGTERM
-        };
+    kill_process_group_with(&SignalReaper, root_pid, KillSignal::from_force(force))
+}
 
-        // Try to kill the process group
-        let pgid = -(root_pid as i32);
-        info!(
-            "Attempting to send {} to process group {} (original PID: {})",
-            sig, pgid, root_pid
-        );
+/// Attempts to signal the group rooted at `root_pid` through the given reaper,
+/// returns true if successful, false if it needs fallback
+pub fn kill_process_group_with(reaper: &dyn Reaper, root_pid: u32, sig: KillSignal) -> bool {
+    try_kill_process_group_with(reaper, root_pid, sig).is_ok()
+}
 
-        match kill(NixPid::from_raw(pgid), sig) {
-            Ok(_) => {
-                info!("Successfully sent {} to process group {}.", sig, pgid);
-                return true;
-            }
-            Err(e_pgid) => {
-                warn!("Failed to send {} to process group {}: {:?}. Will use individual process termination.", sig, pgid, e_pgid);
-            }
-        }
+/// Like [`kill_process_group_with`], but keeps the delivery error
+pub fn try_kill_process_group_with(
+    reaper: &dyn Reaper,
+    root_pid: u32,
+    sig: KillSignal,
+) -> Result<(), String> {
+    if let Some(reason) = safety::group_refusal(root_pid) {
+        warn!(
+            "🛡️ Refusing to send {} to process group of PID {}: {}",
+            sig, root_pid, reason
+        );
+        return Err(format!("Refused: {}", reason));
     }
-
-    #[cfg(windows)]
-    {
-        // Try to use taskkill with /T flag for process tree
-        let pid_str = root_pid.to_string();
-        let mut cmd = std::process::Command::new("taskkill");
-        cmd.arg("/PID").arg(&pid_str);
-        cmd.arg("/T"); // /T kills child processes
-
-        if force {
-            cmd.arg("/F"); // Force kill
+    info!(
+        "Attempting to send {} to process group of PID {} via {}",
+        sig,
+        root_pid,
+        reaper.name()
+    );
+
+    let result = reaper.signal_group(root_pid, sig);
+    match &result {
+        Ok(_) => {
+            info!(
+                "Successfully sent {} to process group of PID {}.",
+                sig, root_pid
+            );
         }
-
-        info!(
-            "Attempting taskkill for PID {} and its children (force: {}).",
-            root_pid, force
-        );
-
-        match cmd.output() {
-            Ok(output) => {
-                if output.status.success() {
-                    info!(
-                        "taskkill for PID {} and its children (force: {}) successful.",
-                        root_pid, force
-                    );
-                    return true;
-                } else {
-                    warn!("taskkill for PID {} and its children (force: {}) failed. Status: {}. Will use individual process termination.", 
-                          root_pid, force, output.status);
-                }
-            }
-            Err(e) => {
-                warn!("Failed to execute taskkill for PID {} and its children (force: {}): {:?}. Will use individual process termination.", 
-                      root_pid, force, e);
-            }
+        Err(e) => {
+            warn!(
+                "Failed to send {} to process group of PID {}: {}. Will use individual process termination.",
+                sig, root_pid, e
+            );
         }
     }
-
-    false
+    result
 }
 
 /// Parses a comma-separated string of PIDs into a HashSet of u32 values
@@ -318,6 +348,133 @@ pub fn parse_target_pids(targets_str: &str) -> Result<HashSet<u32>, String> {
     Ok(pids)
 }
 
+/// A target PID with optional per-target overrides of the first ladder rung
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetSpec {
+    pub pid: u32,
+    /// Seconds to wait after the first signal, instead of the vengeance delay
+    pub delay: Option<u64>,
+    /// Signal to send first, instead of SIGTERM
+    pub signal: Option<KillSignal>,
+    /// Targets that must be dead before this one is signalled
+    pub after: Vec<u32>,
+    /// Targets that may only be signalled once this one is dead
+    pub before: Vec<u32>,
+    /// Budgets that take the target down early when exceeded
+    pub limits: ResourceLimits,
+}
+
+/// Collects `after`/`before` relationships as "PID stops after these PIDs"
+pub fn target_dependencies(specs: &[TargetSpec]) -> HashMap<u32, BTreeSet<u32>> {
+    let mut after: HashMap<u32, BTreeSet<u32>> = HashMap::new();
+    for spec in specs {
+        after
+            .entry(spec.pid)
+            .or_default()
+            .extend(spec.after.iter().copied());
+        for &later in &spec.before {
+            after.entry(later).or_default().insert(spec.pid);
+        }
+    }
+    after
+}
+
+/// Parses targets such as `1234:delay=30:signal=INT,5678:after=1234+4321:max_rss=2G:max_cpu=90%/60s`
+///
+/// Returns the targets in ascending PID order; a PID listed twice keeps its last options.
+/// Dependencies must name other targets and must not form a cycle.
+pub fn parse_target_specs(targets_str: &str) -> Result<Vec<TargetSpec>, String> {
+    let mut specs = BTreeMap::new();
+
+    for s in targets_str.split(',') {
+        let trimmed = s.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        let mut parts = trimmed.split(':');
+        let pid = parts.next().unwrap_or_default().trim();
+        let mut spec = TargetSpec {
+            pid: pid.parse().map_err(|_| format!("Invalid PID: {}", pid))?,
+            ..TargetSpec::default()
+        };
+        for option in parts {
+            match option.trim().split_once('=') {
+                Some(("delay", delay)) => {
+                    spec.delay = Some(
+                        delay
+                            .parse()
+                            .map_err(|_| format!("Invalid delay: {}", delay))?,
+                    );
+                }
+                Some(("signal", signal)) => spec.signal = Some(signal.parse()?),
+                Some(("after", pids)) => spec.after.extend(parse_dependency_pids(pids)?),
+                Some(("before", pids)) => spec.before.extend(parse_dependency_pids(pids)?),
+                Some(("max_rss", size)) => spec.limits.max_rss = Some(size.parse()?),
+                Some(("max_cpu", limit)) => spec.limits.max_cpu = Some(limit.parse()?),
+                _ => return Err(format!("Invalid target option: {}", option)),
+            }
+        }
+        specs.insert(spec.pid, spec);
+    }
+
+    if specs.is_empty() {
+        return Err("No valid PIDs provided".to_string());
+    }
+
+    let specs: Vec<TargetSpec> = specs.into_values().collect();
+    let pids: Vec<u32> = specs.iter().map(|spec| spec.pid).collect();
+    for spec in &specs {
+        if let Some(other) = spec
+            .after
+            .iter()
+            .chain(&spec.before)
+            .find(|pid| !pids.contains(pid))
+        {
+            return Err(format!(
+                "Target {} depends on {}, which is not a target",
+                spec.pid, other
+            ));
+        }
+    }
+    vengeance::shutdown_waves(&pids, &target_dependencies(&specs))?;
+
+    Ok(specs)
+}
+
+/// Parses a duration such as `90`, `90s`, `30m`, `2h` or `1d` (bare numbers are seconds)
+pub fn parse_duration(duration: &str) -> Result<Duration, String> {
+    let trimmed = duration.trim();
+    let invalid = || format!("Invalid duration: {}", trimmed);
+    let (number, unit) = match trimmed.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&trimmed[..i], c),
+        _ => (trimmed, 's'),
+    };
+    let seconds: u64 = match unit {
+        's' => 1,
+        'm' => 60,
+        'h' => 60 * 60,
+        'd' => 24 * 60 * 60,
+        _ => return Err(invalid()),
+    };
+    let number: u64 = number.trim().parse().map_err(|_| invalid())?;
+    number
+        .checked_mul(seconds)
+        .map(Duration::from_secs)
+        .ok_or_else(invalid)
+}
+
+/// Parses the `+`-separated PIDs of an `after=` or `before=` option
+fn parse_dependency_pids(pids: &str) -> Result<Vec<u32>, String> {
+    pids.split('+')
+        .map(|pid| {
+            pid.trim()
+                .parse()
+                .map_err(|_| format!("Invalid PID: {}", pid))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -355,10 +512,140 @@ mod tests {
         assert_eq!(result.unwrap_err(), "Invalid PID: abc");
     }
 
+    #[test]
+    fn test_parse_target_specs() {
+        let specs = parse_target_specs("5678, 1234:delay=30:signal=INT ,9:signal=sigkill").unwrap();
+        assert_eq!(
+            specs,
+            vec![
+                TargetSpec {
+                    pid: 9,
+                    signal: Some(KillSignal::Kill),
+                    ..TargetSpec::default()
+                },
+                TargetSpec {
+                    pid: 1234,
+                    delay: Some(30),
+                    signal: Some(KillSignal::Int),
+                    ..TargetSpec::default()
+                },
+                TargetSpec {
+                    pid: 5678,
+                    ..TargetSpec::default()
+                },
+            ]
+        );
+
+        assert_eq!(
+            parse_target_specs("1234:delay=soon").unwrap_err(),
+            "Invalid delay: soon"
+        );
+        assert_eq!(
+            parse_target_specs("1234:wait=1").unwrap_err(),
+            "Invalid target option: wait=1"
+        );
+        assert_eq!(
+            parse_target_specs("1234:signal=NOPE").unwrap_err(),
+            "Invalid signal: NOPE"
+        );
+        assert_eq!(parse_target_specs("abc").unwrap_err(), "Invalid PID: abc");
+
+        let limited = parse_target_specs("7:max_rss=2G:max_cpu=90%/60s").unwrap();
+        assert_eq!(
+            limited[0].limits,
+            ResourceLimits {
+                max_rss: Some(ByteSize(2 << 30)),
+                max_cpu: Some(CpuLimit {
+                    percent: 90,
+                    window: Duration::from_secs(60),
+                }),
+            }
+        );
+        assert_eq!(
+            parse_target_specs("7:max_rss=lots").unwrap_err(),
+            "Invalid size: lots"
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("30m"), Ok(Duration::from_secs(1800)));
+        assert_eq!(parse_duration(" 2h "), Ok(Duration::from_secs(7200)));
+        assert_eq!(parse_duration("1d"), Ok(Duration::from_secs(86400)));
+        assert_eq!(parse_duration("5y").unwrap_err(), "Invalid duration: 5y");
+        assert!(parse_duration("m").is_err());
+    }
+
+    #[test]
+    fn test_parse_target_dependencies() {
+        // api (10) before db (20) before storage (30)
+        let specs = parse_target_specs("10:before=20,20,30:after=20").unwrap();
+        let after = target_dependencies(&specs);
+        assert_eq!(after[&20], BTreeSet::from([10]));
+        assert_eq!(after[&30], BTreeSet::from([20]));
+
+        assert_eq!(
+            parse_target_specs("10:after=20,20:after=30,30:after=10").unwrap_err(),
+            "Dependency cycle between targets: [10, 20, 30]"
+        );
+        assert_eq!(
+            parse_target_specs("10:after=20+40,20").unwrap_err(),
+            "Target 10 depends on 40, which is not a target"
+        );
+    }
+
     #[test]
     fn test_parse_target_pids_all_invalid() {
         let result = parse_target_pids(",,,");
         assert!(result.is_err());
         assert_eq!(result.unwrap_err(), "No valid PIDs provided");
     }
+
+    fn sample_table() -> MemoryTable {
+        // 100
+        // ├── 101
+        // │   └── 103
+        // │       └── 104
+        // └── 102
+        let table = MemoryTable::new();
+        table.spawn(1, 0);
+        table.spawn(100, 1);
+        table.spawn(102, 100);
+        table.spawn(101, 100);
+        table.spawn(103, 101);
+        table.spawn(104, 103);
+        table.spawn(200, 1);
+        table
+    }
+
+    #[test]
+    fn test_build_process_tree_with_memory_table() {
+        let tree = build_process_tree_with(&sample_table(), 100);
+
+        assert_eq!(tree.len(), 5);
+        assert!(!tree.contains_key(&200));
+        assert_eq!(tree[&100].children, vec![101, 102]);
+        assert_eq!(tree[&103].parent_pid, 101);
+        assert_eq!(tree[&104].depth, 3);
+    }
+
+    #[test]
+    fn test_build_process_tree_for_missing_root() {
+        let tree = build_process_tree_with(&sample_table(), 999);
+
+        assert_eq!(tree.len(), 1);
+        assert!(tree[&999].children.is_empty());
+    }
+
+    #[test]
+    fn test_kill_ordering() {
+        let tree = build_process_tree_with(&sample_table(), 100);
+
+        assert_eq!(get_pids_by_depth(&tree), vec![100, 101, 102, 103, 104]);
+        assert_eq!(
+            get_processes_in_dfs_order(&tree, 100),
+            vec![104, 103, 101, 102, 100]
+        );
+    }
 }
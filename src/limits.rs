@@ -0,0 +1,373 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System};
+
+use crate::parse_duration;
+
+/// An amount of memory, written like `512M` or `2G` (powers of 1024)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl FromStr for ByteSize {
+    type Err = String;
+
+    /// Parses a number of bytes with an optional `K`, `M`, `G` or `T` suffix
+    fn from_str(size: &str) -> Result<Self, Self::Err> {
+        let trimmed = size.trim();
+        let upper = trimmed.to_ascii_uppercase();
+        let digits = upper.trim_end_matches('B');
+        let (number, unit) = match digits.char_indices().last() {
+            Some((i, c)) if c.is_ascii_alphabetic() => (&digits[..i], c),
+            _ => (digits, 'B'),
+        };
+        let shift = match unit {
+            'B' => 0,
+            'K' => 10,
+            'M' => 20,
+            'G' => 30,
+            'T' => 40,
+            _ => return Err(format!("Invalid size: {}", trimmed)),
+        };
+        let number: u64 = number
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid size: {}", trimmed))?;
+        number
+            .checked_mul(1 << shift)
+            .map(ByteSize)
+            .ok_or_else(|| format!("Invalid size: {}", trimmed))
+    }
+}
+
+impl fmt::Display for ByteSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let units = [(40, "T"), (30, "G"), (20, "M"), (10, "K")];
+        match units.iter().find(|(shift, _)| self.0 >= 1 << shift) {
+            Some((shift, unit)) => {
+                write!(f, "{:.1}{}", self.0 as f64 / (1u64 << shift) as f64, unit)
+            }
+            None => write!(f, "{}B", self.0),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ByteSize {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let size = String::deserialize(deserializer)?;
+        size.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// CPU usage that must not be exceeded for longer than `window`
+///
+/// The percentage is of one CPU, so a process busy on two cores uses 200%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuLimit {
+    pub percent: u32,
+    pub window: Duration,
+}
+
+impl FromStr for CpuLimit {
+    type Err = String;
+
+    /// Parses `90%/60s`; without a window a single tick above the limit is enough
+    ///
+    /// `/` works everywhere, target specs included. The older `90%:60s` is
+    /// still accepted wherever `:` is not an option separator.
+    fn from_str(limit: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid CPU limit: {}", limit.trim());
+        let (percent, window) = match limit.trim().split_once([':', '/']) {
+            Some((percent, window)) => (percent, parse_duration(window)?),
+            None => (limit.trim(), Duration::ZERO),
+        };
+        let percent = percent
+            .trim()
+            .trim_end_matches('%')
+            .parse()
+            .map_err(|_| invalid())?;
+        Ok(CpuLimit { percent, window })
+    }
+}
+
+impl fmt::Display for CpuLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.percent)?;
+        if !self.window.is_zero() {
+            write!(f, " for {}s", self.window.as_secs())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for CpuLimit {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let limit = String::deserialize(deserializer)?;
+        limit.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Budgets a target's process tree must stay within while the dog is alive
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    pub max_rss: Option<ByteSize>,
+    pub max_cpu: Option<CpuLimit>,
+}
+
+impl ResourceLimits {
+    pub fn is_empty(&self) -> bool {
+        self.max_rss.is_none() && self.max_cpu.is_none()
+    }
+
+    /// These limits, falling back to `defaults` for the ones not set
+    pub fn or(self, defaults: ResourceLimits) -> ResourceLimits {
+        ResourceLimits {
+            max_rss: self.max_rss.or(defaults.max_rss),
+            max_cpu: self.max_cpu.or(defaults.max_cpu),
+        }
+    }
+}
+
+impl fmt::Display for ResourceLimits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let limits: Vec<String> = [
+            self.max_rss.map(|rss| format!("RSS {}", rss)),
+            self.max_cpu.map(|cpu| format!("CPU {}", cpu)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        write!(f, "{}", limits.join(", "))
+    }
+}
+
+/// Memory and CPU used by a group of processes, summed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Usage {
+    pub rss_bytes: u64,
+    /// Percent of one CPU since the previous sample
+    pub cpu_percent: u32,
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RSS {}, CPU {}%",
+            ByteSize(self.rss_bytes),
+            self.cpu_percent
+        )
+    }
+}
+
+/// Measures how much memory and CPU processes use
+pub trait UsageProbe: Send + Sync {
+    /// Sums the usage of the given processes; missing ones count as zero
+    fn usage(&self, pids: &[u32]) -> Usage;
+
+    /// CPU time a process has used since it started, if it exists
+    fn cpu_time(&self, pid: u32) -> Option<Duration>;
+}
+
+/// Usage measured with sysinfo
+///
+/// CPU usage is computed between two samples, so it reads zero the first time
+/// a process is measured.
+#[derive(Debug, Default)]
+pub struct SysinfoProbe {
+    system: Mutex<System>,
+}
+
+impl SysinfoProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl UsageProbe for SysinfoProbe {
+    fn usage(&self, pids: &[u32]) -> Usage {
+        let pids: Vec<Pid> = pids.iter().map(|&pid| Pid::from_u32(pid)).collect();
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            ProcessRefreshKind::nothing().with_memory().with_cpu(),
+        );
+        let (rss_bytes, cpu) = pids
+            .iter()
+            .filter_map(|pid| system.process(*pid))
+            .fold((0, 0.0), |(rss, cpu), process| {
+                (rss + process.memory(), cpu + process.cpu_usage())
+            });
+        Usage {
+            rss_bytes,
+            cpu_percent: cpu.round() as u32,
+        }
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        let pid = Pid::from_u32(pid);
+        let mut system = self.system.lock().unwrap();
+        system.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            true,
+            ProcessRefreshKind::nothing().with_cpu(),
+        );
+        system
+            .process(pid)
+            .map(|process| Duration::from_millis(process.accumulated_cpu_time()))
+    }
+}
+
+/// Usage set by hand, for tests and simulations
+#[derive(Debug, Default)]
+pub struct MemoryProbe {
+    usage: Mutex<HashMap<u32, Usage>>,
+    cpu_times: Mutex<HashMap<u32, Duration>>,
+}
+
+impl MemoryProbe {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, pid: u32, usage: Usage) {
+        self.usage.lock().unwrap().insert(pid, usage);
+    }
+
+    pub fn set_cpu_time(&self, pid: u32, cpu_time: Duration) {
+        self.cpu_times.lock().unwrap().insert(pid, cpu_time);
+    }
+}
+
+impl UsageProbe for MemoryProbe {
+    fn usage(&self, pids: &[u32]) -> Usage {
+        let usage = self.usage.lock().unwrap();
+        pids.iter()
+            .filter_map(|pid| usage.get(pid))
+            .fold(Usage::default(), |total, usage| Usage {
+                rss_bytes: total.rss_bytes + usage.rss_bytes,
+                cpu_percent: total.cpu_percent + usage.cpu_percent,
+            })
+    }
+
+    fn cpu_time(&self, pid: u32) -> Option<Duration> {
+        self.cpu_times.lock().unwrap().get(&pid).copied()
+    }
+}
+
+/// Remembers since when each target has been above its CPU limit
+#[derive(Debug, Default)]
+pub struct LimitTracker {
+    cpu_above_since: HashMap<u32, Instant>,
+}
+
+impl LimitTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describes the limit `target` breaks with `usage` sampled at `now`, if any
+    ///
+    /// The CPU limit is only broken once usage stayed above it for its whole
+    /// window; one sample below resets the count.
+    pub fn check(
+        &mut self,
+        target: u32,
+        limits: &ResourceLimits,
+        usage: Usage,
+        now: Instant,
+    ) -> Option<String> {
+        if let Some(max_rss) = limits.max_rss {
+            if usage.rss_bytes > max_rss.0 {
+                return Some(format!("RSS {}", max_rss));
+            }
+        }
+        let max_cpu = limits.max_cpu?;
+        if usage.cpu_percent <= max_cpu.percent {
+            self.cpu_above_since.remove(&target);
+            return None;
+        }
+        let since = *self.cpu_above_since.entry(target).or_insert(now);
+        (now.duration_since(since) >= max_cpu.window).then(|| format!("CPU {}", max_cpu))
+    }
+
+    /// Forgets a target, e.g. once it has been taken down
+    pub fn forget(&mut self, target: u32) {
+        self.cpu_above_since.remove(&target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_limits() {
+        assert_eq!("2G".parse(), Ok(ByteSize(2 << 30)));
+        assert_eq!("512m".parse(), Ok(ByteSize(512 << 20)));
+        assert_eq!("100KB".parse(), Ok(ByteSize(100 << 10)));
+        assert_eq!("4096".parse(), Ok(ByteSize(4096)));
+        assert!("2X".parse::<ByteSize>().is_err());
+        assert!("G".parse::<ByteSize>().is_err());
+        assert_eq!(ByteSize(3 << 29).to_string(), "1.5G");
+
+        assert_eq!(
+            "90%/60s".parse(),
+            Ok(CpuLimit {
+                percent: 90,
+                window: Duration::from_secs(60)
+            })
+        );
+        assert_eq!("90%:60s".parse::<CpuLimit>(), "90%/60s".parse());
+        assert_eq!(
+            "150%/2m".parse(),
+            Ok(CpuLimit {
+                percent: 150,
+                window: Duration::from_secs(120)
+            })
+        );
+        assert_eq!("90".parse::<CpuLimit>().unwrap().window, Duration::ZERO);
+        assert!("lots".parse::<CpuLimit>().is_err());
+    }
+
+    #[test]
+    fn test_cpu_limit_needs_a_sustained_breach() {
+        let limits = ResourceLimits {
+            max_rss: Some(ByteSize(1000)),
+            max_cpu: Some("90%/60s".parse().unwrap()),
+        };
+        let busy = Usage {
+            rss_bytes: 10,
+            cpu_percent: 95,
+        };
+        let idle = Usage {
+            rss_bytes: 10,
+            cpu_percent: 5,
+        };
+        let start = Instant::now();
+        let at = |secs| start + Duration::from_secs(secs);
+        let mut tracker = LimitTracker::new();
+
+        assert_eq!(tracker.check(1, &limits, busy, at(0)), None);
+        assert_eq!(tracker.check(1, &limits, busy, at(30)), None);
+        assert_eq!(tracker.check(1, &limits, idle, at(45)), None);
+        assert_eq!(tracker.check(1, &limits, busy, at(50)), None);
+        assert_eq!(
+            tracker.check(1, &limits, busy, at(110)),
+            Some("CPU 90% for 60s".to_string())
+        );
+
+        let bloated = Usage {
+            rss_bytes: 2000,
+            cpu_percent: 0,
+        };
+        assert_eq!(
+            tracker.check(2, &limits, bloated, at(0)),
+            Some("RSS 1000B".to_string())
+        );
+    }
+}
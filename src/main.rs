@@ -1,52 +1,425 @@
-use clap::Parser;
+use chrono::{DateTime, FixedOffset, Local};
+use clap::{Parser, Subcommand};
 use log::{info, warn};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::Write;
-use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use process_wick::config::parse_deadline;
+#[cfg(unix)]
+use process_wick::daemon::{
+    send_request, serve_control, ContractControl, Daemon, Request, WATCH_CONTRACT_ID,
+};
+use process_wick::hang::parse_ticks;
+use process_wick::proc_events;
+use process_wick::safety::foreign_owner;
+use process_wick::tree::user_names;
+#[cfg(unix)]
+use process_wick::{
+    config::{HooksConfig, TargetConfig},
+    SelfSignalPolicy, TargetSet,
+};
 use process_wick::{
-    build_process_tree, get_dog_pid, get_pids_by_depth, is_process_alive, kill_process_group,
-    parse_target_pids, send_signal_to_pids,
+    describe_ladder, native_table, parse_duration, parse_protect_rules, parse_target_pids,
+    parse_target_specs, render_tree, shutdown_waves, tree_report, ByteSize, CancellationToken,
+    Config, Contract, ContractConfig, CpuLimit, EventBus, GracefulStop, HookConfig, HookKind,
+    NamespacePolicy, Origin, PidNamespaces, ResourceLimits, Safety, Selector, Vengeance,
 };
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use tokio::sync::broadcast::error::RecvError;
 
 #[derive(Parser, Debug)]
 #[command(name = "process-wick")]
 #[command(about = "The John Wick of processes — Kill dangling processes when the parent dies 🔫💥", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    watch: WatchArgs,
+
+    /// Path to the log file. If not provided, logs will only be printed to console.
+    #[arg(long, global = true)]
+    log_file: Option<String>,
+
+    /// Log level (error, warn, info, debug, trace). Default: info
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+struct WatchArgs {
     /// PID of the process to watch (the dog). If not provided, defaults to parent PID.
     #[arg(long)]
     dog: Option<u32>,
 
-    /// PIDs of the processes to kill when the dog dies (comma-separated).
-    #[arg(long, required = true)]
-    targets: String,
+    /// Time in seconds between each check on the dog. Default: 3
+    #[arg(long)]
+    tick: Option<u64>,
+
+    /// Shell command to run as soon as the dog is found dead.
+    #[arg(long)]
+    on_dog_death: Option<String>,
 
-    /// Time in seconds to wait after SIGTERM before force-killing.
-    #[arg(long, default_value = "5")]
-    vengeance_delay: u64,
+    /// With --dry-run, print the plan right away instead of waiting for the dog to die.
+    #[arg(long, requires = "dry_run")]
+    now: bool,
 
-    /// Time in seconds between each check on the dog.
-    #[arg(long, default_value = "3")]
-    tick: u64,
+    /// Take a target down on its own, while the dog lives, once its process tree uses more
+    /// memory than this, e.g. 2G. Targets may set their own with max_rss=.
+    #[arg(long, value_name = "SIZE")]
+    max_rss: Option<ByteSize>,
 
-    /// Path to the log file. If not provided, logs will only be printed to console.
+    /// Take a target down on its own once its process tree stays above this CPU usage
+    /// (percent of one CPU) for the given time, e.g. 90%/60s. Targets may set their own with
+    /// max_cpu=, written the same way.
+    #[arg(long, value_name = "PERCENT/DURATION")]
+    max_cpu: Option<CpuLimit>,
+
+    /// Execute vengeance once process-wick has been watching this long, even if the dog is
+    /// still alive, e.g. 30m.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    max_lifetime: Option<Duration>,
+
+    /// Execute vengeance at this RFC 3339 time, even if the dog is still alive,
+    /// e.g. 2025-06-01T18:00:00Z.
+    #[arg(long, value_name = "RFC3339", value_parser = parse_deadline)]
+    deadline: Option<DateTime<FixedOffset>>,
+
+    /// Also count the dog as dead once it looks hung: stuck in uninterruptible sleep or
+    /// stopped, or using no CPU while an exited child waits to be reaped. Every check is logged.
     #[arg(long)]
-    log_file: Option<String>,
+    detect_hang: bool,
 
-    /// Log level (error, warn, info, debug, trace). Default: info
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    /// With --detect-hang, consecutive ticks the dog may stay stuck. Default: 5
+    #[arg(long, value_name = "TICKS", requires = "detect_hang", value_parser = parse_ticks)]
+    hang_stuck_ticks: Option<u32>,
+
+    /// With --detect-hang, consecutive ticks the dog may leave an exited child unreaped
+    /// without using CPU. Default: 10
+    #[arg(long, value_name = "TICKS", requires = "detect_hang", value_parser = parse_ticks)]
+    hang_idle_ticks: Option<u32>,
+
+    /// Follow fork/exit events from the Linux kernel proc connector (needs CAP_NET_ADMIN),
+    /// falling back to polling when it is unavailable.
+    #[arg(long)]
+    proc_connector: bool,
+
+    /// Path of a control socket for adding/removing targets or disarming while watching.
+    #[cfg(unix)]
+    #[arg(long)]
+    control_socket: Option<PathBuf>,
+
+    /// What to do when process-wick itself receives SIGTERM or SIGINT: `execute` runs vengeance
+    /// right away, `abandon` disarms and exits cleanly, `ignore` keeps watching.
+    /// SIGHUP always reloads the targets from --config and logs other changes as needing a restart.
+    #[cfg(unix)]
+    #[arg(long, value_name = "execute|abandon|ignore", default_value = "abandon")]
+    on_self_signal: SelfSignalPolicy,
+
+    #[command(flatten)]
+    contract: ContractArgs,
+}
+
+/// Flags shared by watching a dog and killing right away
+#[derive(clap::Args, Debug, Clone)]
+struct ContractArgs {
+    /// PIDs of the processes to kill (comma-separated).
+    /// Each may override its first signal and wait, e.g. 1234:delay=30:signal=INT,5678,
+    /// and order the shutdown with after=/before= (PIDs joined by +), e.g. 5678:after=1234
+    #[arg(long, required_unless_present = "config")]
+    targets: Option<String>,
+
+    /// Time in seconds to wait after SIGTERM before force-killing. Default: 5
+    #[arg(long)]
+    vengeance_delay: Option<u64>,
+
+    /// Stop a target with a command or an HTTP POST to a localhost URL instead of SIGTERM,
+    /// as PID=ACTION (e.g. 1234=redis-cli shutdown, 5678=http://127.0.0.1:8080/stop). Repeatable.
+    #[arg(long, value_name = "PID=ACTION")]
+    graceful_stop: Vec<String>,
+
+    /// Also take down processes sharing a target's process group, or the session the target
+    /// created with setsid, such as daemonized grandchildren reparented to init. Every process
+    /// of that session except its leader is taken down, including unrelated jobs in its terminal.
+    #[arg(long)]
+    sweep_session: bool,
+
+    /// Shell command to run right before the first signal is sent.
+    #[arg(long)]
+    before_kill: Option<String>,
+
+    /// Shell command to run once all targets are dead.
+    #[arg(long)]
+    after_kill: Option<String>,
+
+    /// Time in seconds each hook may run before it is killed. Default: 30
+    #[arg(long)]
+    hook_timeout: Option<u64>,
+
+    /// Processes that must never be signalled, e.g. name:sshd,uid:0,pid:1234.
+    /// PID 0, PID 1, process-wick and its ancestors are always refused.
+    #[arg(long)]
+    protect: Option<String>,
+
+    /// How --dog, --targets and --graceful-stop PIDs relate to PID namespaces: `strict` refuses
+    /// processes in another namespace than process-wick, `self` accepts them as process-wick
+    /// sees them, and a PID means they are local to that process's namespace and get translated.
+    #[arg(long, value_name = "strict|self|PID", default_value = "strict")]
+    pid_namespace: NamespacePolicy,
+
+    /// TOML file declaring one or more contracts. Command-line flags override its values.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Print the plan (which signal goes to which PID, and when) instead of sending anything.
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Print contract events (dog death, signals, exits, ...) to stdout as JSON lines.
+    #[arg(long)]
+    json_events: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run the vengeance sequence on the targets right away, without watching a dog.
+    /// Exits non-zero unless every target and its descendants are confirmed dead.
+    Kill(ContractArgs),
+
+    /// Validate a configuration file and print the contracts it declares.
+    CheckConfig {
+        /// Path of the TOML file to check.
+        path: PathBuf,
+    },
+
+    /// Print the process tree under a PID, as vengeance would discover it.
+    Tree {
+        /// Root of the tree.
+        pid: u32,
+
+        /// Print the tree as JSON instead of a table.
+        #[arg(long)]
+        json: bool,
+
+        /// Also list what --sweep-session would catch.
+        #[arg(long)]
+        sweep_session: bool,
+    },
+
+    /// Run a single long-lived wick holding many contracts, managed over a Unix socket.
+    #[cfg(unix)]
+    Daemon {
+        /// Path of the control socket to listen on.
+        #[arg(long)]
+        socket: PathBuf,
+
+        /// Time in seconds between each check on the dogs.
+        #[arg(long, default_value = "3")]
+        tick: u64,
+
+        /// Processes no registered contract may signal, e.g. name:sshd,uid:0,pid:1234.
+        /// PID 0, PID 1, process-wick and its ancestors are always refused.
+        #[arg(long)]
+        protect: Option<String>,
+
+        /// Print the events of every contract to stdout as JSON lines.
+        #[arg(long)]
+        json_events: bool,
+    },
+
+    /// Register a contract with a running daemon.
+    #[cfg(unix)]
+    Register {
+        /// Path of the daemon's control socket.
+        #[arg(long)]
+        socket: PathBuf,
+
+        /// Name for the contract. Generated by the daemon if not provided.
+        #[arg(long)]
+        id: Option<String>,
+
+        /// PID of the process to watch (the dog).
+        #[arg(long)]
+        dog: u32,
+
+        /// PIDs of the processes to kill when the dog dies (comma-separated).
+        #[arg(long)]
+        targets: String,
+
+        /// Time in seconds to wait after SIGTERM before force-killing.
+        #[arg(long, default_value = "5")]
+        vengeance_delay: u64,
+
+        /// Shell command to run as soon as the dog is found dead.
+        #[arg(long)]
+        on_dog_death: Option<String>,
+
+        /// Shell command to run right before the first signal is sent.
+        #[arg(long)]
+        before_kill: Option<String>,
+
+        /// Shell command to run once all targets are dead.
+        #[arg(long)]
+        after_kill: Option<String>,
+
+        /// Time in seconds each hook may run before it is killed. Default: 30
+        #[arg(long)]
+        hook_timeout: Option<u64>,
+    },
+
+    /// Disarm and forget a contract held by a running daemon.
+    #[cfg(unix)]
+    Remove {
+        /// Path of the daemon's control socket.
+        #[arg(long)]
+        socket: PathBuf,
+
+        /// ID of the contract to remove.
+        id: String,
+    },
+
+    /// List the contracts held by a running daemon.
+    #[cfg(unix)]
+    List {
+        /// Path of the daemon's control socket.
+        #[arg(long)]
+        socket: PathBuf,
+    },
+
+    /// Add a target to a contract that is still being watched.
+    #[cfg(unix)]
+    AddTarget {
+        /// Path of the control socket (daemon or --control-socket of a watching wick).
+        #[arg(long)]
+        socket: PathBuf,
+
+        /// ID of the contract. A watching wick exposes its contract as "default".
+        #[arg(long, default_value = WATCH_CONTRACT_ID)]
+        contract: String,
+
+        /// PID to add.
+        pid: u32,
+    },
+
+    /// Remove a target from a contract that is still being watched.
+    #[cfg(unix)]
+    RemoveTarget {
+        /// Path of the control socket (daemon or --control-socket of a watching wick).
+        #[arg(long)]
+        socket: PathBuf,
+
+        /// ID of the contract. A watching wick exposes its contract as "default".
+        #[arg(long, default_value = WATCH_CONTRACT_ID)]
+        contract: String,
+
+        /// PID to remove.
+        pid: u32,
+    },
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    let config_path = match &args.command {
+        Some(Command::Kill(kill)) => kill.config.as_deref(),
+        _ => args.watch.contract.config.as_deref(),
+    };
+    let config = config_path.map(load_config_or_exit);
+    init_logging(&args, config.as_ref());
+
+    match args.command {
+        None => watch(args.watch, config).await,
+        Some(Command::Kill(kill_args)) => kill(kill_args, config).await,
+        Some(Command::CheckConfig { path }) => check_config(&path),
+        Some(Command::Tree {
+            pid,
+            json,
+            sweep_session,
+        }) => print_tree(pid, json, sweep_session),
+        #[cfg(unix)]
+        Some(Command::Daemon {
+            socket,
+            tick,
+            protect,
+            json_events,
+        }) => daemon(socket, tick, protect, json_events).await,
+        #[cfg(unix)]
+        Some(Command::Register {
+            socket,
+            id,
+            dog,
+            targets,
+            vengeance_delay,
+            on_dog_death,
+            before_kill,
+            after_kill,
+            hook_timeout,
+        }) => {
+            let targets = parse_targets_or_exit(&targets);
+            let hook = |command: Option<String>| {
+                command.map(|command| HookConfig {
+                    command,
+                    timeout: None,
+                })
+            };
+            let hooks = HooksConfig {
+                on_dog_death: hook(on_dog_death),
+                before_kill: hook(before_kill),
+                after_kill: hook(after_kill),
+                timeout: hook_timeout,
+            };
+            request(
+                &socket,
+                Request::Register {
+                    id,
+                    dog,
+                    targets,
+                    vengeance_delay,
+                    hooks,
+                },
+            )
+            .await
+        }
+        #[cfg(unix)]
+        Some(Command::Remove { socket, id }) => request(&socket, Request::Remove { id }).await,
+        #[cfg(unix)]
+        Some(Command::List { socket }) => request(&socket, Request::List).await,
+        #[cfg(unix)]
+        Some(Command::AddTarget {
+            socket,
+            contract,
+            pid,
+        }) => request(&socket, Request::AddTarget { id: contract, pid }).await,
+        #[cfg(unix)]
+        Some(Command::RemoveTarget {
+            socket,
+            contract,
+            pid,
+        }) => request(&socket, Request::RemoveTarget { id: contract, pid }).await,
+    }
+}
+
+fn init_logging(args: &Args, config: Option<&Config>) {
+    let logging = config.map(|config| &config.logging);
+    let log_level = args
+        .log_level
+        .as_deref()
+        .or_else(|| logging.and_then(|logging| logging.level.as_deref()))
+        .unwrap_or("info");
+    let log_file = args
+        .log_file
+        .as_deref()
+        .or_else(|| logging.and_then(|logging| logging.file.as_deref()));
 
     // Initialize logging
     let mut logger = env_logger::Builder::from_default_env();
-    logger.filter_level(match args.log_level.to_lowercase().as_str() {
+    logger.filter_level(match log_level.to_lowercase().as_str() {
         "error" => log::LevelFilter::Error,
         "warn" => log::LevelFilter::Warn,
         "info" => log::LevelFilter::Info,
@@ -64,134 +437,928 @@ async fn main() {
             record.args()
         )
     });
-    if let Some(log_path) = &args.log_file {
+    if let Some(log_path) = log_file {
         let log_file = File::create(log_path).expect("Failed to create log file");
         let log_file = Box::new(log_file);
         logger.target(env_logger::Target::Pipe(log_file));
     }
     logger.init();
+}
 
-    let dog_pid = get_dog_pid(args.dog);
-    let targets = match parse_target_pids(&args.targets) {
-        Ok(pids) => pids,
+/// Parses `--targets` into a sorted list, exiting with an error message on failure
+fn parse_targets_or_exit(targets: &str) -> Vec<u32> {
+    match parse_target_pids(targets) {
+        Ok(pids) => {
+            let mut pids: Vec<u32> = pids.into_iter().collect();
+            pids.sort_unstable();
+            pids
+        }
         Err(e) => {
             eprintln!("Error parsing targets: {}", e);
             std::process::exit(1);
         }
+    }
+}
+
+/// Reads a configuration file, exiting with an error message on failure
+fn load_config_or_exit(path: &std::path::Path) -> Config {
+    Config::load(path).unwrap_or_else(|e| {
+        eprintln!("Invalid configuration: {}", e);
+        std::process::exit(1);
+    })
+}
+
+fn print_tree(pid: u32, json: bool, sweep: bool) {
+    let root =
+        tree_report(native_table().as_ref(), pid, &user_names(), sweep).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        });
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&root).expect("Failed to serialize tree")
+        );
+    } else {
+        print!("{}", render_tree(&root));
+    }
+}
+
+fn check_config(path: &std::path::Path) {
+    let config = load_config_or_exit(path);
+    println!(
+        "✅ {} declares {} contract(s)",
+        path.display(),
+        config.contracts.len()
+    );
+    for contract in &config.contracts {
+        let targets: Vec<String> = contract.targets.iter().map(|t| t.to_string()).collect();
+        println!(
+            "  {}: dog {}, targets [{}], tick {}s, ladder [{}]",
+            contract.id.as_deref().unwrap_or_default(),
+            contract.dog.as_ref().unwrap_or(&Selector::Parent),
+            targets.join(", "),
+            contract.tick().as_secs(),
+            describe_ladder(&contract.ladder())
+        );
+        let hooks = contract.hooks.hooks();
+        for kind in [
+            HookKind::OnDogDeath,
+            HookKind::BeforeKill,
+            HookKind::AfterKill,
+        ] {
+            if let Some(hook) = hooks.get(kind) {
+                println!(
+                    "    {}: {} (timeout {}s)",
+                    kind,
+                    hook.command,
+                    hook.timeout.as_secs()
+                );
+            }
+        }
+    }
+}
+
+/// Merges the configuration file (if any) with the command-line flags, which take precedence
+fn contract_configs(
+    args: &ContractArgs,
+    config: Option<Config>,
+) -> Result<Vec<ContractConfig>, String> {
+    let mut contracts = match config {
+        Some(config) => config.contracts,
+        None => vec![ContractConfig {
+            id: Some("c1".to_string()),
+            ..ContractConfig::default()
+        }],
     };
-    info!("🐶 Watching dog PID: {}", dog_pid);
+
+    if args.targets.is_some() && contracts.len() > 1 {
+        return Err(
+            "--dog and --targets can only override a configuration with a single contract"
+                .to_string(),
+        );
+    }
+
+    for contract in &mut contracts {
+        if let Some(targets) = &args.targets {
+            contract.targets = parse_target_specs(targets)
+                .unwrap_or_else(|e| {
+                    eprintln!("Error parsing targets: {}", e);
+                    std::process::exit(1);
+                })
+                .into_iter()
+                .map(Into::into)
+                .collect();
+        }
+        if let Some(delay) = args.vengeance_delay {
+            contract.vengeance_delay = Some(delay);
+            contract.ladder = None;
+        }
+
+        let hooks = &mut contract.hooks;
+        let cli_hooks = [
+            (&mut hooks.before_kill, &args.before_kill),
+            (&mut hooks.after_kill, &args.after_kill),
+        ];
+        for (hook, command) in cli_hooks {
+            if let Some(command) = command {
+                *hook = Some(HookConfig {
+                    command: command.clone(),
+                    timeout: None,
+                });
+            }
+        }
+        if let Some(timeout) = args.hook_timeout {
+            hooks.timeout = Some(timeout);
+            for hook in [
+                &mut hooks.on_dog_death,
+                &mut hooks.before_kill,
+                &mut hooks.after_kill,
+            ]
+            .into_iter()
+            .flatten()
+            {
+                hook.timeout = None;
+            }
+        }
+    }
+    Ok(contracts)
+}
+
+/// Like [`contract_configs`], plus the flags that only make sense while watching a dog
+fn watch_contract_configs(
+    args: &WatchArgs,
+    config: Option<Config>,
+) -> Result<Vec<ContractConfig>, String> {
+    let mut contracts = contract_configs(&args.contract, config)?;
+    if args.dog.is_some() && contracts.len() > 1 {
+        return Err(
+            "--dog and --targets can only override a configuration with a single contract"
+                .to_string(),
+        );
+    }
+
+    for contract in &mut contracts {
+        if let Some(dog) = args.dog {
+            contract.dog = Some(Selector::Pid(dog));
+        }
+        if let Some(tick) = args.tick {
+            contract.tick = Some(tick);
+        }
+        if let Some(max_rss) = args.max_rss {
+            contract.max_rss = Some(max_rss);
+        }
+        if let Some(max_cpu) = args.max_cpu {
+            contract.max_cpu = Some(max_cpu);
+        }
+        if let Some(lifetime) = args.max_lifetime {
+            contract.max_lifetime = Some(lifetime);
+        }
+        if let Some(deadline) = args.deadline {
+            contract.deadline = Some(deadline);
+        }
+        if args.detect_hang {
+            let mut hang = contract.hang.unwrap_or_default();
+            if let Some(ticks) = args.hang_stuck_ticks {
+                hang.stuck_ticks = ticks;
+            }
+            if let Some(ticks) = args.hang_idle_ticks {
+                hang.idle_ticks = ticks;
+            }
+            contract.hang = Some(hang);
+        }
+        if let Some(command) = &args.on_dog_death {
+            contract.hooks.on_dog_death = Some(HookConfig {
+                command: command.clone(),
+                timeout: None,
+            });
+        }
+    }
+    Ok(contracts)
+}
+
+/// Parses `--graceful-stop PID=ACTION` values, exiting with an error message on failure
+fn parse_graceful_stops_or_exit(values: &[String]) -> HashMap<u32, GracefulStop> {
+    values
+        .iter()
+        .map(|value| {
+            let (pid, action) = value
+                .split_once('=')
+                .ok_or_else(|| format!("Expected PID=ACTION, got {}", value))?;
+            let pid = pid
+                .trim()
+                .parse()
+                .map_err(|_| format!("Invalid PID: {}", pid))?;
+            Ok((pid, action.parse()?))
+        })
+        .collect::<Result<_, String>>()
+        .unwrap_or_else(|e| {
+            eprintln!("Error parsing --graceful-stop: {}", e);
+            std::process::exit(1);
+        })
+}
+
+/// A contract whose targets are resolved and whose vengeance is ready to run
+struct Armed {
+    id: String,
+    config: ContractConfig,
+    targets: Vec<u32>,
+    /// The target entry each PID was armed with
+    entries: BTreeMap<u32, TargetConfig>,
+    vengeance: Vengeance,
+    limits: HashMap<u32, ResourceLimits>,
+}
+
+/// A watched contract, as SIGHUP reloads see it
+#[cfg(unix)]
+struct Running {
+    id: String,
+    config: ContractConfig,
+    entries: BTreeMap<u32, TargetConfig>,
+    targets: TargetSet,
+}
+
+/// Builds the deny list from the configuration file and `--protect`, exiting on error
+fn safety_or_exit(args: &ContractArgs, config: Option<&Config>) -> Safety {
+    let mut protect = config
+        .map(|config| config.protect.clone())
+        .unwrap_or_default();
+    if let Some(rules) = &args.protect {
+        protect.extend(parse_protect_rules(rules).unwrap_or_else(|e| {
+            eprintln!("Error parsing --protect: {}", e);
+            std::process::exit(1);
+        }));
+    }
+    Safety::new(protect)
+}
+
+/// Resolves every contract's targets and builds its vengeance, exiting on any error
+///
+/// Shared by `watch` and `kill`, so both take targets down the same way.
+fn arm(
+    args: &ContractArgs,
+    configs: Vec<ContractConfig>,
+    safety: &Safety,
+    events: &EventBus,
+) -> Vec<Armed> {
+    let mut graceful_stops = parse_graceful_stops_or_exit(&args.graceful_stop);
+    let multiple = configs.len() > 1;
+    let namespaces = PidNamespaces::new();
+    if let Some(own) = namespaces.own() {
+        info!("🪪 process-wick is {}", own);
+    }
+    if let NamespacePolicy::Translate(reference) = args.pid_namespace {
+        graceful_stops = graceful_stops
+            .into_iter()
+            .map(|(pid, stop)| Ok((namespaces.translate(pid, reference)?, stop)))
+            .collect::<Result<_, String>>()
+            .unwrap_or_else(|e| {
+                eprintln!("Error translating --graceful-stop: {}", e);
+                std::process::exit(1);
+            });
+    }
+
+    let mut armed = Vec::new();
+    for config in configs {
+        if multiple {
+            info!("📜 Contract {}", config.id.as_deref().unwrap_or_default());
+        }
+        let contract = arm_contract(
+            config,
+            args.pid_namespace,
+            args.sweep_session,
+            safety,
+            events,
+            &mut graceful_stops,
+        );
+        armed.push(contract.unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }));
+    }
+
+    if let Some(pid) = graceful_stops.keys().next() {
+        eprintln!("Error: --graceful-stop {} is not a target", pid);
+        std::process::exit(1);
+    }
+    armed
+}
+
+/// Resolves one contract's targets and builds its vengeance
+///
+/// Takes the graceful stops of its targets out of `graceful_stops`. Also arms
+/// the contracts registered with a daemon.
+fn arm_contract(
+    mut config: ContractConfig,
+    pid_namespace: NamespacePolicy,
+    sweep_session: bool,
+    safety: &Safety,
+    events: &EventBus,
+    graceful_stops: &mut HashMap<u32, GracefulStop>,
+) -> Result<Armed, String> {
+    let table = native_table();
+    let namespaces = PidNamespaces::new();
+    let id = config.id.clone().unwrap_or_default();
+    if let NamespacePolicy::Translate(reference) = pid_namespace {
+        config
+            .map_pids(|pid| namespaces.translate(pid, reference))
+            .map_err(|e| format!("Error translating contract {}: {}", id, e))?;
+    }
+    let resolved_targets = config
+        .resolve_targets(table.as_ref())
+        .map_err(|e| format!("Error resolving contract {}: {}", id, e))?;
+
+    let targets: Vec<u32> = resolved_targets.keys().copied().collect();
     info!("🎯 Targets: {:?}", targets);
+    for &pid in &targets {
+        safety
+            .check(table.as_ref(), pid)
+            .and_then(|()| namespaces.check(pid, pid_namespace))
+            .map_err(|reason| format!("Refusing target {}: {}", pid, reason))?;
+        if let Some(uid) = foreign_owner(table.as_ref(), pid) {
+            warn!(
+                "👤 Target PID {} belongs to uid {}, not to the user process-wick runs as",
+                pid, uid
+            );
+        }
+    }
+    let mut vengeance = Vengeance::new(config.vengeance_delay())
+        .with_ladder(config.ladder())
+        .with_safety(safety.clone())
+        .with_events(events.clone());
+    // Daemonized grandchildren keep their session and group after losing their parent
+    if sweep_session || config.sweep_session {
+        for &pid in &targets {
+            if let Some(origin) = Origin::of(table.as_ref(), pid) {
+                info!("🧬 PID {} recorded with {}", pid, origin);
+                vengeance = vengeance.with_origin(pid, origin);
+            }
+        }
+    }
+    let dependencies = config
+        .resolve_dependencies(&resolved_targets)
+        .map_err(|e| format!("Error resolving contract {}: {}", id, e))?;
+    for (&pid, after) in &dependencies {
+        for &other in after {
+            vengeance = vengeance
+                .with_dependency(pid, other)
+                .map_err(|e| format!("Error resolving contract {}: {}", id, e))?;
+        }
+    }
+    if let Ok(waves) = shutdown_waves(&targets, &dependencies) {
+        if waves.len() > 1 {
+            info!("🌊 Shutdown waves: {:?}", waves);
+        }
+    }
+
+    let ladder = config.ladder();
+    let mut limits = HashMap::new();
+    for (&pid, target) in &resolved_targets {
+        let own = target.limits.or(config.limits());
+        if !own.is_empty() {
+            info!("📏 PID {} is limited to {}", pid, own);
+            limits.insert(pid, own);
+        }
+        if let Some(own) = target.ladder(&ladder) {
+            info!(
+                "⏱️ PID {} has its own ladder: {}",
+                pid,
+                describe_ladder(&own)
+            );
+            vengeance = vengeance.with_target_ladder(pid, own);
+        }
+        if let Some(stop) = graceful_stops.remove(&pid).or_else(|| target.stop.clone()) {
+            info!("🛎️ PID {} will be asked to stop with: {}", pid, stop);
+            vengeance = vengeance.with_graceful_stop(pid, stop);
+        }
+    }
+    let entries = resolved_targets
+        .iter()
+        .map(|(&pid, &target)| (pid, target.clone()))
+        .collect();
+    Ok(Armed {
+        id,
+        config,
+        targets,
+        entries,
+        vengeance,
+        limits,
+    })
+}
+
+/// Builds the contract watching `dog_pid` for an armed contract
+///
+/// Shared by `watch` and the daemon, so both watch dogs the same way.
+fn watch_contract(armed: &Armed, dog_pid: u32) -> Contract {
+    info!("🐶 Watching dog PID: {}", dog_pid);
     info!(
         "⏳ Tick every {}s, vengeance in {}s",
-        args.tick, args.vengeance_delay
+        armed.config.tick().as_secs(),
+        armed.vengeance.delay().as_secs()
     );
-    let running = Arc::new(AtomicBool::new(true));
-    let r = running.clone();
-    tokio::spawn(async move {
-        loop {
-            if !is_process_alive(dog_pid) {
-                warn!("💀 Dog died. Unleashing vengeance.");
+    let mut contract = Contract::new(dog_pid, armed.targets.clone(), armed.vengeance.clone())
+        .with_tick(armed.config.tick())
+        .with_hooks(armed.config.hooks.hooks());
+    for (&pid, &limits) in &armed.limits {
+        contract = contract.with_limits(pid, limits);
+    }
+    if let Some(lifetime) = armed.config.max_lifetime {
+        contract = contract.with_max_lifetime(lifetime);
+    }
+    if let Some(deadline) = armed.config.deadline {
+        contract = contract.with_deadline(deadline.into());
+    }
+    if let Some(hang) = armed.config.hang {
+        info!(
+            "🩺 Counting the dog as dead after {} ticks stuck or {} ticks idle with exited children",
+            hang.stuck_ticks, hang.idle_ticks
+        );
+        contract = contract.with_hang_detection(hang);
+    }
+    if let Some(deadline) = contract.deadline() {
+        info!(
+            "⏰ Vengeance at {} at the latest",
+            DateTime::<Local>::from(deadline).format("%Y-%m-%d %H:%M:%S")
+        );
+    }
+    contract
+}
 
-                // Step 1: Try group killing first for all targets
-                let mut targets_needing_individual_kill: Vec<u32> = Vec::new();
+async fn watch(args: WatchArgs, config: Option<Config>) {
+    let safety = safety_or_exit(&args.contract, config.as_ref());
+    #[cfg(unix)]
+    let startup = config.clone();
+    let configs = watch_contract_configs(&args, config).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
+    });
 
-                for &pid in &targets {
-                    info!("⚠️ Attempting group kill for PID {}", pid);
-                    let group_kill_successful = kill_process_group(pid, false);
+    // All contracts share one event stream and are disarmed together
+    let events = EventBus::new();
+    let cancel = CancellationToken::new();
+    let fire = CancellationToken::new();
+    let connector = args
+        .proc_connector
+        .then(proc_events::open_or_poll)
+        .flatten()
+        .map(Arc::new);
+    let mut contracts = Vec::new();
+    let mut ids = Vec::new();
+    #[cfg(unix)]
+    let mut running = Vec::new();
+    for armed in arm(&args.contract, configs, &safety, &events) {
+        let dog_pid = armed
+            .config
+            .resolve_dog(native_table().as_ref())
+            .unwrap_or_else(|e| {
+                eprintln!("Error resolving contract {}: {}", armed.id, e);
+                std::process::exit(1);
+            });
+        let contract = watch_contract(&armed, dog_pid)
+            .with_cancellation(cancel.clone())
+            .with_firing(fire.clone());
+        ids.push(armed.id);
+        #[cfg(unix)]
+        running.push(Running {
+            id: ids.last().cloned().unwrap_or_default(),
+            config: armed.config,
+            entries: armed.entries,
+            targets: contract.targets().clone(),
+        });
+        contracts.push(match &connector {
+            Some(connector) => contract.with_proc_connector(connector.clone()),
+            None => contract,
+        });
+    }
 
-                    if !group_kill_successful {
-                        info!("⚠️ Group kill failed for PID {}, will use individual process termination", pid);
-                        targets_needing_individual_kill.push(pid);
+    if args.contract.dry_run {
+        info!("📝 Dry run: nothing will be signalled.");
+        let handles: Vec<_> = ids
+            .into_iter()
+            .zip(contracts)
+            .map(|(id, contract)| {
+                tokio::spawn(async move {
+                    if args.now || contract.wait_for_trigger().await.is_some() {
+                        print_plan(&id, &contract);
                     }
-                }
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.await;
+        }
+        return;
+    }
 
-                // Step 2: If group killing failed for any targets, build fresh process trees and kill individually
-                let mut all_pids_to_kill: Vec<u32> = Vec::new();
-
-                if !targets_needing_individual_kill.is_empty() {
-                    info!("🔍 Building fresh process trees for individual termination");
-
-                    for &pid in &targets_needing_individual_kill {
-                        info!("⚠️ Building fresh process tree for PID {}", pid);
-                        let process_tree = build_process_tree(pid);
-                        let pids_in_order = get_pids_by_depth(&process_tree);
-
-                        info!(
-                            "📋 PID {} has {} child processes: {:?}",
-                            pid,
-                            pids_in_order.len(),
-                            pids_in_order
-                        );
-
-                        // Add all PIDs from this tree to our kill list
-                        for &tree_pid in &pids_in_order {
-                            if !all_pids_to_kill.contains(&tree_pid) {
-                                all_pids_to_kill.push(tree_pid);
-                            }
-                        }
+    let printer = args.contract.json_events.then(|| print_events(&events));
+
+    // SIGUSR1 disarms the contract: the dog is shutting down cleanly and will stop its own targets
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let token = cancel.clone();
+        match signal(SignalKind::user_defined1()) {
+            Ok(mut sigusr1) => {
+                tokio::spawn(async move {
+                    if sigusr1.recv().await.is_some() {
+                        info!("🕊️ Received SIGUSR1, disarming.");
+                        token.cancel();
                     }
+                });
+            }
+            Err(e) => warn!(
+                "Failed to install SIGUSR1 handler, disarm unavailable: {}",
+                e
+            ),
+        }
+    }
 
-                    info!("🎯 Total PIDs to kill individually: {:?}", all_pids_to_kill);
+    #[cfg(unix)]
+    handle_self_signals(args.clone(), safety, startup, running, cancel.clone(), fire);
 
-                    // Step 3: Send SIGTERM to individual PIDs
-                    info!("⚠️ Sending SIGTERM to individual processes");
-                    send_signal_to_pids(&all_pids_to_kill, false);
-                }
+    #[cfg(unix)]
+    let control = args.control_socket.map(|socket| {
+        if contracts.len() > 1 {
+            eprintln!("Error: --control-socket requires a single contract");
+            std::process::exit(1);
+        }
+        let handler = Arc::new(ContractControl::new(&contracts[0]));
+        let stop = CancellationToken::new();
+        let cancel = stop.clone();
+        let server = tokio::spawn(async move {
+            if let Err(e) = serve_control(socket.clone(), cancel, handler).await {
+                warn!("Failed to serve control socket {}: {}", socket.display(), e);
+            }
+        });
+        (stop, server)
+    });
 
-                // Step 4: Wait for graceful termination
-                info!(
-                    "⏳ Waiting {} seconds for graceful termination...",
-                    args.vengeance_delay
-                );
-                tokio::time::sleep(Duration::from_secs(args.vengeance_delay)).await;
+    let handles: Vec<_> = contracts
+        .into_iter()
+        .map(|contract| tokio::spawn(async move { contract.run().await }))
+        .collect();
+    for handle in handles {
+        let _ = handle.await;
+    }
+    #[cfg(unix)]
+    if let Some((stop, server)) = control {
+        stop.cancel();
+        let _ = server.await;
+    }
+    // The printer stops once every sender of the bus is gone
+    drop(events);
+    if let Some(printer) = printer {
+        let _ = printer.await;
+    }
+}
 
-                // Step 5: Refresh the kill list, adding new PIDs but preserving original ones
-                let mut final_kill_list: Vec<u32> = all_pids_to_kill.clone();
-                let mut targets_needing_individual_force_kill: Vec<u32> = Vec::new();
+/// Handles signals sent to process-wick itself while it watches
+///
+/// SIGTERM and SIGINT follow `--on-self-signal`; SIGHUP reloads the targets
+/// from the configuration file and logs the other changes, which need a
+/// restart. `startup` is the file as process-wick started with it.
+#[cfg(unix)]
+fn handle_self_signals(
+    args: WatchArgs,
+    safety: Safety,
+    startup: Option<Config>,
+    running: Vec<Running>,
+    cancel: CancellationToken,
+    fire: CancellationToken,
+) {
+    use tokio::signal::unix::{signal, Signal, SignalKind};
 
-                for &pid in &targets {
-                    info!("⚠️ Forcefully attempting group kill for PID {}", pid);
-                    let group_kill_successful = kill_process_group(pid, true);
+    let install = |kind: SignalKind, name: &str| {
+        signal(kind)
+            .map_err(|e| warn!("Failed to install {} handler: {}", name, e))
+            .ok()
+    };
+    let mut sigterm = install(SignalKind::terminate(), "SIGTERM");
+    let mut sigint = install(SignalKind::interrupt(), "SIGINT");
+    let mut sighup = install(SignalKind::hangup(), "SIGHUP");
+    async fn recv(signal: &mut Option<Signal>) {
+        match signal {
+            Some(signal) => {
+                signal.recv().await;
+            }
+            None => std::future::pending().await,
+        }
+    }
 
-                    if !group_kill_successful {
-                        info!("⚠️ Group kill failed for PID {}, will use individual process force termination", pid);
-                        targets_needing_individual_force_kill.push(pid);
-                    }
-                }
-                if !targets_needing_individual_force_kill.is_empty() {
-                    info!("🔍 Refreshing process trees to catch any new processes");
-
-                    for &pid in &targets_needing_individual_force_kill {
-                        let process_tree = build_process_tree(pid);
-                        let pids_in_order = get_pids_by_depth(&process_tree);
-
-                        // Add any new PIDs that weren't in the original list
-                        for &tree_pid in &pids_in_order {
-                            if !final_kill_list.contains(&tree_pid) {
-                                info!("➕ Adding new PID {} to kill list", tree_pid);
-                                final_kill_list.push(tree_pid);
-                            }
+    tokio::spawn(async move {
+        loop {
+            let name = tokio::select! {
+                _ = recv(&mut sigterm) => "SIGTERM",
+                _ = recv(&mut sigint) => "SIGINT",
+                _ = recv(&mut sighup) => {
+                    info!("🔁 Received SIGHUP, reloading targets.");
+                    match reload_targets(&args, &safety, startup.as_ref(), &running) {
+                        Ok(keys) if keys.is_empty() => {
+                            info!("🔁 Targets reloaded, no other setting changed.")
+                        }
+                        Ok(keys) => warn!(
+                            "🔁 Targets reloaded. Not applied until restart: {}",
+                            keys.join(", ")
+                        ),
+                        Err(e) => {
+                            warn!("Failed to reload, keeping the current targets: {}", e)
                         }
                     }
+                    continue;
+                }
+            };
+            match args.on_self_signal {
+                SelfSignalPolicy::Execute => {
+                    warn!("📡 Received {}, executing vengeance now.", name);
+                    fire.cancel();
+                }
+                SelfSignalPolicy::Abandon => {
+                    info!("🛑 Received {}. Contracts are abandoned.", name);
+                    cancel.cancel();
+                }
+                SelfSignalPolicy::Ignore => {
+                    info!("🙉 Received {}, ignoring it and keeping watch.", name)
+                }
+            }
+        }
+    });
+}
+
+/// Re-reads the configuration file and brings each running contract's targets in line with it
+///
+/// Nothing changes unless the whole file validates and resolves and every
+/// target can be applied as written. Targets only change membership: a PID
+/// whose target entry has its own settings, or whose settings changed, is
+/// refused, as is a new PID the contract's budgets or session sweep would
+/// have to cover. Returns the keys that changed since `startup` but need a
+/// restart.
+#[cfg(unix)]
+fn reload_targets(
+    args: &WatchArgs,
+    safety: &Safety,
+    startup: Option<&Config>,
+    running: &[Running],
+) -> Result<Vec<String>, String> {
+    let path = args
+        .contract
+        .config
+        .as_ref()
+        .ok_or("there is no --config to reload")?;
+    let reloaded = Config::load(path)?;
+    let restart_keys = startup
+        .map(|startup| startup.restart_keys(&reloaded))
+        .unwrap_or_default();
+    let configs = watch_contract_configs(args, Some(reloaded))?;
+    let table = native_table();
+    let namespaces = PidNamespaces::new();
 
-                    info!(
-                        "🎯 Final kill list (preserving original + new PIDs): {:?}",
-                        final_kill_list
-                    );
+    let mut plan = Vec::new();
+    for mut config in configs {
+        let id = config.id.clone().unwrap_or_default();
+        // New contracts are among the restart keys
+        let Some(contract) = running.iter().find(|contract| contract.id == id) else {
+            continue;
+        };
+        if contract.targets.is_sealed() {
+            return Err(format!("contract {} already started vengeance", id));
+        }
+        if let NamespacePolicy::Translate(reference) = args.contract.pid_namespace {
+            config.map_pids(|pid| namespaces.translate(pid, reference))?;
+        }
+        let resolved = config
+            .resolve_targets(table.as_ref())
+            .map_err(|e| format!("contract {}: {}", id, e))?;
+        let current: BTreeSet<u32> = contract.targets.pids().into_iter().collect();
+        let covered = !contract.config.limits().is_empty()
+            || contract.config.sweep_session
+            || args.contract.sweep_session;
+        for (&pid, &target) in &resolved {
+            safety
+                .check(table.as_ref(), pid)
+                .and_then(|()| namespaces.check(pid, args.contract.pid_namespace))
+                .map_err(|reason| format!("refusing target {}: {}", pid, reason))?;
+            let unapplied = match contract.entries.get(&pid) {
+                Some(armed) if !armed.same_settings(target) => Some("changes its settings"),
+                Some(_) => None,
+                None if target.has_settings() => Some("has settings of its own"),
+                None if covered && !current.contains(&pid) => {
+                    Some("would need the contract's budgets or session sweep")
                 }
+                None => None,
+            };
+            if let Some(why) = unapplied {
+                return Err(format!(
+                    "contract {}: target {} {} for PID {}, which needs a restart",
+                    id, target, why, pid
+                ));
+            }
+        }
+        plan.push((
+            id,
+            &contract.targets,
+            resolved.into_keys().collect::<Vec<_>>(),
+        ));
+    }
 
-                // Step 6: Force kill all processes in the final list
-                info!("🔪 Force killing all processes in final list");
-                send_signal_to_pids(&final_kill_list, true);
+    for (id, targets, wanted) in plan {
+        match targets.replace(wanted) {
+            Ok((added, removed)) => {
+                for pid in removed {
+                    info!("➖ Target {} removed from contract {} by reload", pid, id);
+                }
+                for pid in added {
+                    info!("➕ Target {} added to contract {} by reload", pid, id);
+                }
+            }
+            Err(e) => warn!("Contract {} keeps its targets: {}", id, e),
+        }
+    }
+    Ok(restart_keys)
+}
 
-                info!("🧘 Process-wick retires in peace.");
-                r.store(false, Ordering::SeqCst);
-                break;
+/// Prints every event of the bus to stdout as a JSON line until the bus closes
+fn print_events(events: &EventBus) -> tokio::task::JoinHandle<()> {
+    let mut events = events.subscribe();
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => match serde_json::to_string(&event) {
+                    Ok(line) => println!("{}", line),
+                    Err(e) => warn!("Failed to serialize event {:?}: {}", event, e),
+                },
+                Err(RecvError::Lagged(missed)) => {
+                    warn!("Event printer fell behind, {} events dropped", missed)
+                }
+                Err(RecvError::Closed) => break,
             }
-            tokio::time::sleep(Duration::from_secs(args.tick)).await;
         }
+    })
+}
+
+/// Takes the targets down right away, then verifies that nothing they spawned survived
+///
+/// There is no dog: hooks see `WICK_DOG_PID=0`.
+async fn kill(args: ContractArgs, config: Option<Config>) {
+    let safety = safety_or_exit(&args, config.as_ref());
+    let configs = contract_configs(&args, config).unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        std::process::exit(1);
     });
-    while running.load(Ordering::SeqCst) {
-        tokio::time::sleep(Duration::from_millis(500)).await;
+
+    let events = EventBus::new();
+    let contracts: Vec<(String, Contract)> = arm(&args, configs, &safety, &events)
+        .into_iter()
+        .map(|armed| {
+            let hooks = armed.config.hooks.hooks();
+            let contract = Contract::new(0, armed.targets, armed.vengeance).with_hooks(hooks);
+            (armed.id, contract)
+        })
+        .collect();
+
+    if args.dry_run {
+        info!("📝 Dry run: nothing will be signalled.");
+        for (id, contract) in &contracts {
+            print_plan(id, contract);
+        }
+        return;
+    }
+
+    // Everything that has to be gone afterwards, discovered before the first signal
+    let mut doomed = BTreeSet::new();
+    for (_, contract) in &contracts {
+        for pid in contract.targets().pids() {
+            doomed.extend(
+                contract
+                    .vengeance()
+                    .discover(pid)
+                    .into_iter()
+                    .map(|(pid, _)| pid),
+            );
+        }
+    }
+
+    let printer = args.json_events.then(|| print_events(&events));
+    let handles: Vec<_> = contracts
+        .into_iter()
+        .map(|(_, contract)| tokio::spawn(async move { contract.execute().await }))
+        .collect();
+    for handle in handles {
+        let _ = handle.await;
+    }
+    drop(events);
+    if let Some(printer) = printer {
+        let _ = printer.await;
+    }
+
+    // Zombies are dead, just not reaped yet
+    let table = native_table();
+    let survivors: Vec<u32> = doomed
+        .iter()
+        .copied()
+        .filter(|&pid| table.process(pid).is_some_and(|p| p.state != Some('Z')))
+        .collect();
+    if !survivors.is_empty() {
+        eprintln!("Survivors: {:?}", survivors);
+        std::process::exit(1);
+    }
+    info!("✅ All {} processes confirmed dead", doomed.len());
+}
+
+/// Prints what the contract would do if its dog died now
+fn print_plan(id: &str, contract: &Contract) {
+    let targets = contract.targets().pids();
+    let steps = contract.vengeance().plan(&targets);
+    let end = steps.iter().map(|step| step.at).max().unwrap_or_default();
+
+    match contract.dog_pid() {
+        0 => println!("Plan for contract {}:", id),
+        dog => println!("Plan for contract {} (dog PID {}):", id, dog),
+    }
+    for kind in [HookKind::OnDogDeath, HookKind::BeforeKill] {
+        if let Some(hook) = contract.hooks().get(kind) {
+            println!("  T+0s {} hook: {}", kind, hook.command);
+        }
+    }
+    for step in &steps {
+        println!("  {}", step);
+    }
+    if let Some(hook) = contract.hooks().get(HookKind::AfterKill) {
+        println!(
+            "  T+{}s {} hook: {}",
+            end.as_secs(),
+            HookKind::AfterKill,
+            hook.command
+        );
+    }
+}
+
+#[cfg(unix)]
+async fn daemon(socket: PathBuf, tick: u64, protect: Option<String>, json_events: bool) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let protect = protect
+        .map(|rules| parse_protect_rules(&rules))
+        .transpose()
+        .unwrap_or_else(|e| {
+            eprintln!("Error parsing --protect: {}", e);
+            std::process::exit(1);
+        })
+        .unwrap_or_default();
+    let safety = Safety::new(protect);
+    let events = EventBus::new();
+    // Abandoned contracts still hold the bus, so the printer just stops with the daemon
+    let _printer = json_events.then(|| print_events(&events));
+
+    let cancel = CancellationToken::new();
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        let mut sigterm =
+            signal(SignalKind::terminate()).expect("Failed to install SIGTERM handler");
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = sigterm.recv() => {}
+        }
+        info!("🛑 Shutting down daemon. Registered contracts are abandoned.");
+        token.cancel();
+    });
+
+    // Registrations are armed and watched exactly like watch-mode contracts
+    let arm = move |config: ContractConfig| {
+        let armed = arm_contract(
+            config,
+            NamespacePolicy::default(),
+            false,
+            &safety,
+            &events,
+            &mut HashMap::new(),
+        )?;
+        let dog_pid = armed.config.resolve_dog(native_table().as_ref())?;
+        Ok(watch_contract(&armed, dog_pid))
+    };
+    let daemon = Arc::new(Daemon::new(Duration::from_secs(tick), arm));
+    if let Err(e) = daemon.serve(socket.clone(), cancel).await {
+        eprintln!("Failed to serve {}: {}", socket.display(), e);
+        std::process::exit(1);
+    }
+}
+
+/// Sends a request to a daemon and prints its JSON response, exiting non-zero on failure
+#[cfg(unix)]
+async fn request(socket: &Path, request: Request) {
+    match send_request(socket, &request).await {
+        Ok(response) => {
+            println!(
+                "{}",
+                serde_json::to_string(&response).expect("Failed to serialize response")
+            );
+            if !response.ok {
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            eprintln!("Failed to reach daemon at {}: {}", socket.display(), e);
+            std::process::exit(1);
+        }
     }
 }
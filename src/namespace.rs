@@ -0,0 +1,236 @@
+use log::{debug, info};
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// Inode of the initial PID namespace (`PROC_PID_INIT_INO` in the kernel)
+pub const INIT_PID_NAMESPACE: u64 = 0xEFFF_FFFC;
+
+/// How `--dog` and `--targets` PIDs relate to PID namespaces
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NamespacePolicy {
+    /// Refuse processes living in another PID namespace than process-wick
+    #[default]
+    Strict,
+    /// PIDs are as process-wick sees them, whatever namespace they live in
+    Own,
+    /// PIDs are local to the namespace of the given process and get translated
+    Translate(u32),
+}
+
+impl FromStr for NamespacePolicy {
+    type Err = String;
+
+    /// Parses `strict`, `self` or the PID of a process in the namespace to translate from
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy.trim() {
+            "strict" => Ok(NamespacePolicy::Strict),
+            "self" => Ok(NamespacePolicy::Own),
+            pid => pid
+                .parse()
+                .map(NamespacePolicy::Translate)
+                .map_err(|_| format!("Invalid PID namespace: {}", policy)),
+        }
+    }
+}
+
+/// A process's PID namespace and its PID at every level, as read from `/proc`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamespacedPid {
+    /// PID as seen by process-wick
+    pub pid: u32,
+    /// Inode of the namespace the process lives in
+    pub namespace: u64,
+    /// `NSpid`: PIDs from process-wick's namespace down to the process's own
+    pub nspid: Vec<u32>,
+}
+
+impl NamespacedPid {
+    /// PID inside the process's own namespace
+    pub fn local_pid(&self) -> u32 {
+        self.nspid.last().copied().unwrap_or(self.pid)
+    }
+}
+
+impl fmt::Display for NamespacedPid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "PID {} in pid:[{}]", self.local_pid(), self.namespace)
+    }
+}
+
+/// Reads PID namespaces from procfs
+#[derive(Debug, Clone)]
+pub struct PidNamespaces {
+    root: PathBuf,
+    own: Option<NamespacedPid>,
+}
+
+impl Default for PidNamespaces {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PidNamespaces {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+
+    /// Reads from an alternative procfs mount (useful for tests and containers)
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        let own = read_namespace(&root, "self", std::process::id());
+        Self { root, own }
+    }
+
+    /// process-wick's own namespace, when procfs exposes it
+    pub fn own(&self) -> Option<&NamespacedPid> {
+        self.own.as_ref()
+    }
+
+    /// Reads the namespace of `pid`, or `None` if it is gone or unreadable
+    pub fn lookup(&self, pid: u32) -> Option<NamespacedPid> {
+        read_namespace(&self.root, &pid.to_string(), pid)
+    }
+
+    /// The PID of `pid` in the initial namespace, if process-wick can see it
+    ///
+    /// Only known when process-wick itself runs in the initial namespace.
+    pub fn host_pid(&self, process: &NamespacedPid) -> Option<u32> {
+        let own = self.own.as_ref()?;
+        (own.namespace == INIT_PID_NAMESPACE)
+            .then(|| process.nspid.first().copied().unwrap_or(process.pid))
+    }
+
+    /// Finds the PID process-wick sees for `local`, a PID inside the namespace of `reference`
+    pub fn translate(&self, local: u32, reference: u32) -> Result<u32, String> {
+        let namespace = self
+            .lookup(reference)
+            .ok_or_else(|| format!("Cannot read the PID namespace of PID {}", reference))?
+            .namespace;
+        let entries = std::fs::read_dir(&self.root)
+            .map_err(|e| format!("Failed to read {}: {}", self.root.display(), e))?;
+        entries
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str()?.parse::<u32>().ok())
+            .filter_map(|pid| self.lookup(pid))
+            .find(|process| process.namespace == namespace && process.local_pid() == local)
+            .map(|process| {
+                info!(
+                    "🪪 PID {} in pid:[{}] is PID {} for process-wick",
+                    local, namespace, process.pid
+                );
+                process.pid
+            })
+            .ok_or_else(|| format!("No PID {} in pid:[{}]", local, namespace))
+    }
+
+    /// Logs where `pid` lives and refuses it under the strict policy when its
+    /// namespace differs from process-wick's
+    pub fn check(&self, pid: u32, policy: NamespacePolicy) -> Result<(), String> {
+        let (Some(own), Some(process)) = (self.own.as_ref(), self.lookup(pid)) else {
+            debug!("PID namespace of PID {} is unknown, not checking it", pid);
+            return Ok(());
+        };
+        match self.host_pid(&process) {
+            Some(host) => info!("🪪 PID {} is {}, host PID {}", pid, process, host),
+            None => info!("🪪 PID {} is {}, host PID unknown", pid, process),
+        }
+        if policy == NamespacePolicy::Strict && process.namespace != own.namespace {
+            return Err(format!(
+                "it lives in pid:[{}] but process-wick runs in pid:[{}]; \
+                 pass --pid-namespace self to use process-wick's PIDs anyway, \
+                 or --pid-namespace <PID> to translate PIDs from that process's namespace",
+                process.namespace, own.namespace
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn read_namespace(root: &std::path::Path, dir: &str, pid: u32) -> Option<NamespacedPid> {
+    let dir = root.join(dir);
+    let link = std::fs::read_link(dir.join("ns").join("pid")).ok()?;
+    let namespace = parse_namespace_link(link.to_str()?)?;
+    let status = std::fs::read_to_string(dir.join("status")).ok()?;
+    let nspid = parse_nspid(&status).unwrap_or_else(|| vec![pid]);
+    Some(NamespacedPid {
+        pid: nspid.first().copied().unwrap_or(pid),
+        namespace,
+        nspid,
+    })
+}
+
+/// Parses the `pid:[4026531836]` target of `/proc/<pid>/ns/pid`
+fn parse_namespace_link(link: &str) -> Option<u64> {
+    link.strip_prefix("pid:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// Parses the `NSpid:` line of `/proc/<pid>/status`
+fn parse_nspid(status: &str) -> Option<Vec<u32>> {
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix("NSpid:"))?;
+    line.split_whitespace()
+        .map(|pid| pid.parse().ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_policy_and_proc_files() {
+        assert_eq!("strict".parse(), Ok(NamespacePolicy::Strict));
+        assert_eq!("self".parse(), Ok(NamespacePolicy::Own));
+        assert_eq!("42".parse(), Ok(NamespacePolicy::Translate(42)));
+        assert!("host".parse::<NamespacePolicy>().is_err());
+
+        assert_eq!(
+            parse_namespace_link("pid:[4026531836]"),
+            Some(INIT_PID_NAMESPACE)
+        );
+        assert_eq!(parse_namespace_link("net:[1]"), None);
+        assert_eq!(
+            parse_nspid("Name:\tsleep\nNSpid:\t4321\t12\t1\nNSsid:\t1\n"),
+            Some(vec![4321, 12, 1])
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_and_translate_across_namespaces() {
+        let root = std::env::temp_dir().join(format!("wick-ns-{}", std::process::id()));
+        let fake = |dir: &str, namespace: u64, nspid: &str| {
+            let dir = root.join(dir);
+            std::fs::create_dir_all(dir.join("ns")).unwrap();
+            std::os::unix::fs::symlink(format!("pid:[{}]", namespace), dir.join("ns/pid")).unwrap();
+            std::fs::write(dir.join("status"), format!("NSpid:\t{}\n", nspid)).unwrap();
+        };
+        fake("self", INIT_PID_NAMESPACE, "900");
+        fake("900", INIT_PID_NAMESPACE, "900");
+        fake("100", INIT_PID_NAMESPACE, "100");
+        // A container whose init is host PID 4000
+        fake("4000", 77, "4000\t1");
+        fake("4012", 77, "4012\t12");
+
+        let namespaces = PidNamespaces::with_root(&root);
+        let container = namespaces.lookup(4012).unwrap();
+        assert_eq!(container.local_pid(), 12);
+        assert_eq!(namespaces.host_pid(&container), Some(4012));
+
+        assert!(namespaces.check(100, NamespacePolicy::Strict).is_ok());
+        assert!(namespaces.check(4012, NamespacePolicy::Strict).is_err());
+        assert!(namespaces.check(4012, NamespacePolicy::Own).is_ok());
+        // Unreadable processes are not refused
+        assert!(namespaces.check(5555, NamespacePolicy::Strict).is_ok());
+
+        assert_eq!(namespaces.translate(12, 4000), Ok(4012));
+        assert_eq!(namespaces.translate(1, 4000), Ok(4000));
+        assert!(namespaces.translate(13, 4000).is_err());
+        assert!(namespaces.translate(12, 5555).is_err());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+use log::{info, warn};
+use tokio::sync::broadcast;
+
+/// Number of kernel events a slow subscriber may fall behind before it lags
+const PROC_EVENT_CAPACITY: usize = 4096;
+
+/// A process lifecycle event reported by the kernel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcEvent {
+    /// `parent` forked a new process `child`
+    Fork { parent: u32, child: u32 },
+    /// Process `pid` exited
+    Exit { pid: u32 },
+}
+
+/// Live fork/exit feed from the Linux kernel proc connector
+///
+/// Unlike polling, it sees forks that live shorter than a tick and reports
+/// exits as they happen. Subscribing to the connector needs `CAP_NET_ADMIN`;
+/// [`ProcConnector::open`] fails without it (and on other platforms), in
+/// which case callers keep polling.
+#[derive(Debug)]
+pub struct ProcConnector {
+    events: broadcast::Sender<ProcEvent>,
+}
+
+impl ProcConnector {
+    /// Subscribes to the proc connector and starts forwarding its events
+    ///
+    /// Events are read on a dedicated thread that lives as long as the process.
+    pub fn open() -> std::io::Result<Self> {
+        #[cfg(target_os = "linux")]
+        {
+            let socket = linux::subscribe()?;
+            let (events, _) = broadcast::channel(PROC_EVENT_CAPACITY);
+            let sender = events.clone();
+            std::thread::Builder::new()
+                .name("proc-connector".to_string())
+                .spawn(move || linux::forward(socket, sender))?;
+            info!("⚡ Subscribed to the kernel proc connector");
+            Ok(Self { events })
+        }
+
+        #[cfg(not(target_os = "linux"))]
+        {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::Unsupported,
+                "the proc connector is only available on Linux",
+            ))
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ProcEvent> {
+        self.events.subscribe()
+    }
+
+    /// A connector fed by hand instead of the kernel
+    #[cfg(test)]
+    pub(crate) fn detached() -> (Self, broadcast::Sender<ProcEvent>) {
+        let (events, _) = broadcast::channel(PROC_EVENT_CAPACITY);
+        (
+            Self {
+                events: events.clone(),
+            },
+            events,
+        )
+    }
+}
+
+/// Opens the connector, or explains why wick keeps polling
+pub fn open_or_poll() -> Option<ProcConnector> {
+    match ProcConnector::open() {
+        Ok(connector) => Some(connector),
+        Err(e) => {
+            warn!(
+                "⚠️ Proc connector unavailable ({}), falling back to polling",
+                e
+            );
+            None
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::ProcEvent;
+    use log::warn;
+    use std::io;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+    use tokio::sync::broadcast;
+
+    const NETLINK_CONNECTOR: libc::c_int = 11;
+    const CN_IDX_PROC: u32 = 1;
+    const CN_VAL_PROC: u32 = 1;
+    const PROC_CN_MCAST_LISTEN: u32 = 1;
+    const PROC_EVENT_FORK: u32 = 0x0000_0001;
+    const PROC_EVENT_EXIT: u32 = 0x8000_0000;
+
+    const NLMSG_HEADER_LEN: usize = 16;
+    const CN_MSG_LEN: usize = 20;
+    /// `what`, `cpu` and `timestamp_ns` precede the event data
+    const PROC_EVENT_HEADER_LEN: usize = 16;
+
+    /// Binds a netlink socket to the proc connector group and asks for events
+    pub(super) fn subscribe() -> io::Result<OwnedFd> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC,
+                NETLINK_CONNECTOR,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut address: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+        address.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        address.nl_pid = 0;
+        address.nl_groups = CN_IDX_PROC;
+        let bound = unsafe {
+            libc::bind(
+                socket.as_raw_fd(),
+                &address as *const libc::sockaddr_nl as *const libc::sockaddr,
+                std::mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        };
+        if bound < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let message = listen_message(std::process::id());
+        let sent = unsafe {
+            libc::send(
+                socket.as_raw_fd(),
+                message.as_ptr() as *const libc::c_void,
+                message.len(),
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    /// `nlmsghdr` + `cn_msg` + `PROC_CN_MCAST_LISTEN`
+    fn listen_message(pid: u32) -> Vec<u8> {
+        let len = NLMSG_HEADER_LEN + CN_MSG_LEN + 4;
+        let mut message = Vec::with_capacity(len);
+        message.extend_from_slice(&(len as u32).to_ne_bytes());
+        message.extend_from_slice(&(libc::NLMSG_DONE as u16).to_ne_bytes());
+        message.extend_from_slice(&0u16.to_ne_bytes()); // flags
+        message.extend_from_slice(&0u32.to_ne_bytes()); // seq
+        message.extend_from_slice(&pid.to_ne_bytes());
+        message.extend_from_slice(&CN_IDX_PROC.to_ne_bytes());
+        message.extend_from_slice(&CN_VAL_PROC.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes()); // seq
+        message.extend_from_slice(&0u32.to_ne_bytes()); // ack
+        message.extend_from_slice(&4u16.to_ne_bytes()); // payload length
+        message.extend_from_slice(&0u16.to_ne_bytes()); // flags
+        message.extend_from_slice(&PROC_CN_MCAST_LISTEN.to_ne_bytes());
+        message
+    }
+
+    /// Reads datagrams until the socket fails, forwarding every process event
+    pub(super) fn forward(socket: OwnedFd, events: broadcast::Sender<ProcEvent>) {
+        let mut buffer = vec![0u8; 8192];
+        loop {
+            let received = unsafe {
+                libc::recv(
+                    socket.as_raw_fd(),
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                    0,
+                )
+            };
+            if received < 0 {
+                let error = io::Error::last_os_error();
+                match error.raw_os_error() {
+                    Some(libc::EINTR) => continue,
+                    // The kernel dropped events; the next tick's rescan catches up
+                    Some(libc::ENOBUFS) => {
+                        warn!("⚠️ Proc connector overflowed, some events were lost");
+                        continue;
+                    }
+                    _ => {
+                        warn!("⚠️ Proc connector failed: {}", error);
+                        return;
+                    }
+                }
+            }
+            for event in parse_datagram(&buffer[..received as usize]) {
+                // Nobody listening is fine; a contract may subscribe later
+                let _ = events.send(event);
+            }
+        }
+    }
+
+    /// Extracts the fork and exit events of whole processes (not threads)
+    pub(super) fn parse_datagram(datagram: &[u8]) -> Vec<ProcEvent> {
+        let u32_at = |bytes: &[u8], offset: usize| {
+            bytes
+                .get(offset..offset + 4)
+                .map(|b| u32::from_ne_bytes([b[0], b[1], b[2], b[3]]))
+        };
+
+        let mut events = Vec::new();
+        let mut rest = datagram;
+        while let Some(len) = u32_at(rest, 0) {
+            let len = len as usize;
+            if len < NLMSG_HEADER_LEN || len > rest.len() {
+                break;
+            }
+            let message = &rest[..len];
+            let data = NLMSG_HEADER_LEN + CN_MSG_LEN;
+            let event = data + PROC_EVENT_HEADER_LEN;
+            let what = u32_at(message, data);
+            match what {
+                Some(PROC_EVENT_FORK) => {
+                    let parent = u32_at(message, event + 4);
+                    let child_pid = u32_at(message, event + 8);
+                    let child = u32_at(message, event + 12);
+                    if let (Some(parent), Some(child_pid), Some(child)) = (parent, child_pid, child)
+                    {
+                        if child_pid == child {
+                            events.push(ProcEvent::Fork { parent, child });
+                        }
+                    }
+                }
+                Some(PROC_EVENT_EXIT) => {
+                    let pid = u32_at(message, event);
+                    let tgid = u32_at(message, event + 4);
+                    if let (Some(pid), Some(tgid)) = (pid, tgid) {
+                        if pid == tgid {
+                            events.push(ProcEvent::Exit { pid });
+                        }
+                    }
+                }
+                _ => {}
+            }
+            // Messages are padded to 4 bytes
+            let aligned = (len + 3) & !3;
+            rest = rest.get(aligned..).unwrap_or_default();
+        }
+        events
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::linux::parse_datagram;
+    use super::*;
+
+    fn message(what: u32, fields: [u32; 4]) -> Vec<u8> {
+        let mut message = vec![0u8; 16 + 20];
+        message.extend_from_slice(&what.to_ne_bytes());
+        message.extend_from_slice(&0u32.to_ne_bytes());
+        message.extend_from_slice(&0u64.to_ne_bytes());
+        for field in fields {
+            message.extend_from_slice(&field.to_ne_bytes());
+        }
+        let len = message.len() as u32;
+        message[..4].copy_from_slice(&len.to_ne_bytes());
+        message
+    }
+
+    #[test]
+    fn test_parse_fork_and_exit() {
+        let mut datagram = message(0x1, [100, 100, 200, 200]);
+        // A new thread of 100, not a process
+        datagram.extend(message(0x1, [100, 100, 201, 200]));
+        datagram.extend(message(0x8000_0000, [150, 150, 0, 9]));
+        // A thread of 150 exiting
+        datagram.extend(message(0x8000_0000, [151, 150, 0, 0]));
+
+        assert_eq!(
+            parse_datagram(&datagram),
+            vec![
+                ProcEvent::Fork {
+                    parent: 100,
+                    child: 200
+                },
+                ProcEvent::Exit { pid: 150 },
+            ]
+        );
+        assert!(parse_datagram(&[1, 2]).is_empty());
+    }
+}
@@ -0,0 +1,422 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex};
+use sysinfo::{
+    Pid, Process, ProcessRefreshKind, ProcessStatus, ProcessesToUpdate, System, UpdateKind,
+};
+
+#[cfg(target_os = "linux")]
+use std::path::{Path, PathBuf};
+#[cfg(target_os = "linux")]
+use std::sync::OnceLock;
+
+/// A single entry of a process table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub parent_pid: u32,
+    /// Start time in seconds since the Unix epoch
+    pub start_time: u64,
+    /// Executable name as reported by the OS (may be truncated, e.g. to 15 bytes on Linux)
+    pub name: String,
+    /// Owner's user ID, where the platform has one
+    pub uid: Option<u32>,
+    /// Process group ID, where the platform has one
+    pub pgid: Option<u32>,
+    /// Session ID, where the platform has one
+    pub sid: Option<u32>,
+    /// Scheduler state as a `ps`-style letter (`R`, `S`, `D`, `Z`, `T`, ...)
+    pub state: Option<char>,
+}
+
+impl ProcessInfo {
+    pub fn new(pid: u32, parent_pid: u32, start_time: u64) -> Self {
+        Self {
+            pid,
+            parent_pid,
+            start_time,
+            name: String::new(),
+            uid: None,
+            pgid: None,
+            sid: None,
+            state: None,
+        }
+    }
+
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn with_uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    pub fn with_group(mut self, pgid: u32, sid: u32) -> Self {
+        self.pgid = Some(pgid);
+        self.sid = Some(sid);
+        self
+    }
+
+    pub fn with_state(mut self, state: char) -> Self {
+        self.state = Some(state);
+        self
+    }
+}
+
+/// Source of process information used by tree discovery and liveness checks
+pub trait ProcessTable: Send + Sync {
+    /// Lists every process currently known to the table
+    fn processes(&self) -> Vec<ProcessInfo>;
+
+    /// Looks up a single process
+    fn process(&self, pid: u32) -> Option<ProcessInfo> {
+        self.processes().into_iter().find(|p| p.pid == pid)
+    }
+
+    /// Returns the parent PID of the given process, if it exists
+    fn parent_pid(&self, pid: u32) -> Option<u32> {
+        self.process(pid).map(|p| p.parent_pid)
+    }
+
+    /// Checks whether the given process exists
+    fn is_alive(&self, pid: u32) -> bool {
+        self.process(pid).is_some()
+    }
+
+    /// Returns the start time of the given process in seconds since the Unix epoch
+    fn start_time(&self, pid: u32) -> Option<u64> {
+        self.process(pid).map(|p| p.start_time)
+    }
+}
+
+/// The leanest process table for this platform
+///
+/// `/proc` on Linux (when mounted), sysinfo everywhere else.
+pub fn native_table() -> Arc<dyn ProcessTable> {
+    #[cfg(target_os = "linux")]
+    {
+        let procfs = ProcfsTable::new();
+        if procfs.is_alive(std::process::id()) {
+            return Arc::new(procfs);
+        }
+    }
+    Arc::new(SysinfoTable)
+}
+
+/// Cross-platform process table backed by sysinfo
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SysinfoTable;
+
+impl ProcessTable for SysinfoTable {
+    fn processes(&self) -> Vec<ProcessInfo> {
+        let mut sys = System::new_all();
+        sys.refresh_all();
+
+        sys.processes()
+            .iter()
+            .map(|(pid, process)| sysinfo_entry(pid.as_u32(), process))
+            .collect()
+    }
+
+    fn process(&self, pid: u32) -> Option<ProcessInfo> {
+        let sys_pid = Pid::from_u32(pid);
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[sys_pid]),
+            true,
+            ProcessRefreshKind::nothing().with_user(UpdateKind::OnlyIfNotSet),
+        );
+
+        sys.process(sys_pid)
+            .map(|process| sysinfo_entry(pid, process))
+    }
+}
+
+fn sysinfo_entry(pid: u32, process: &Process) -> ProcessInfo {
+    let parent_pid = process.parent().unwrap_or(Pid::from(0));
+    let info = ProcessInfo::new(pid, parent_pid.as_u32(), process.start_time())
+        .with_name(process.name().to_string_lossy())
+        .with_state(state_letter(process.status()));
+
+    #[cfg(unix)]
+    let info = {
+        let mut info = info;
+        info.uid = process.user_id().map(|uid| **uid);
+        let pgid = unsafe { libc::getpgid(pid as libc::pid_t) };
+        info.pgid = (pgid >= 0).then_some(pgid as u32);
+        info.sid = process.session_id().map(|sid| sid.as_u32());
+        info
+    };
+    info
+}
+
+/// Maps sysinfo's status onto the letters `ps` uses
+fn state_letter(status: ProcessStatus) -> char {
+    match status {
+        ProcessStatus::Run => 'R',
+        ProcessStatus::Sleep => 'S',
+        ProcessStatus::Idle => 'I',
+        ProcessStatus::UninterruptibleDiskSleep => 'D',
+        ProcessStatus::Stop => 'T',
+        ProcessStatus::Tracing => 't',
+        ProcessStatus::Zombie => 'Z',
+        ProcessStatus::Dead => 'X',
+        ProcessStatus::Wakekill => 'K',
+        ProcessStatus::Waking => 'W',
+        ProcessStatus::Parked => 'P',
+        _ => '?',
+    }
+}
+
+/// Linux process table that reads `/proc/<pid>/stat` directly
+///
+/// Unlike sysinfo, it never reads CPU, memory, command lines or environments:
+/// one `stat` file per process gives the parent, group, session, state and
+/// start time, and the directory's owner gives the user.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+pub struct ProcfsTable {
+    root: PathBuf,
+    boot_time: OnceLock<u64>,
+}
+
+#[cfg(target_os = "linux")]
+impl Default for ProcfsTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProcfsTable {
+    pub fn new() -> Self {
+        Self::with_root("/proc")
+    }
+
+    /// Reads from an alternative procfs mount (useful for tests and containers)
+    pub fn with_root(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            boot_time: OnceLock::new(),
+        }
+    }
+
+    /// Read once: the boot time never changes while the system is up
+    fn boot_time(&self) -> u64 {
+        *self.boot_time.get_or_init(|| {
+            std::fs::read_to_string(self.root.join("stat"))
+                .ok()
+                .and_then(|stat| {
+                    stat.lines()
+                        .find_map(|line| line.strip_prefix("btime "))
+                        .and_then(|btime| btime.trim().parse().ok())
+                })
+                .unwrap_or(0)
+        })
+    }
+
+    fn read_stat(&self, pid_dir: &Path, boot_time: u64) -> Option<ProcessInfo> {
+        use std::os::unix::fs::MetadataExt;
+
+        let stat = std::fs::read_to_string(pid_dir.join("stat")).ok()?;
+        let info = parse_stat(&stat, boot_time, clock_ticks_per_second())?;
+        // /proc/<pid> is owned by the process's effective user
+        match std::fs::metadata(pid_dir) {
+            Ok(metadata) => Some(info.with_uid(metadata.uid())),
+            Err(_) => Some(info),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ProcessTable for ProcfsTable {
+    fn processes(&self) -> Vec<ProcessInfo> {
+        let boot_time = self.boot_time();
+        let Ok(entries) = std::fs::read_dir(&self.root) else {
+            return Vec::new();
+        };
+
+        entries
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_name()
+                    .to_str()
+                    .is_some_and(|name| name.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .filter_map(|entry| self.read_stat(&entry.path(), boot_time))
+            .collect()
+    }
+
+    fn process(&self, pid: u32) -> Option<ProcessInfo> {
+        self.read_stat(&self.root.join(pid.to_string()), self.boot_time())
+    }
+
+    fn is_alive(&self, pid: u32) -> bool {
+        self.root.join(pid.to_string()).join("stat").exists()
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn clock_ticks_per_second() -> u64 {
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if ticks > 0 {
+        ticks as u64
+    } else {
+        100
+    }
+}
+
+/// Parses the contents of `/proc/<pid>/stat`
+///
+/// The command name is wrapped in parentheses and may itself contain spaces or
+/// parentheses, so the remaining fields are read after the last `)`.
+#[cfg(target_os = "linux")]
+fn parse_stat(stat: &str, boot_time: u64, ticks_per_second: u64) -> Option<ProcessInfo> {
+    let (head, rest) = stat.rsplit_once(')')?;
+    let (pid, name) = head.split_once('(')?;
+    let pid = pid.trim().parse().ok()?;
+
+    // Fields after the command name, starting at field 3 (state)
+    let fields: Vec<&str> = rest.split_whitespace().collect();
+    let state = fields.first()?.chars().next()?;
+    let parent_pid = fields.get(1)?.parse().ok()?;
+    let pgid = fields.get(2)?.parse().ok()?;
+    let sid = fields.get(3)?.parse().ok()?;
+    let start_ticks: u64 = fields.get(19)?.parse().ok()?;
+
+    Some(
+        ProcessInfo::new(
+            pid,
+            parent_pid,
+            boot_time + start_ticks / ticks_per_second.max(1),
+        )
+        .with_name(name)
+        .with_state(state)
+        .with_group(pgid, sid),
+    )
+}
+
+/// In-memory process table for tests and simulations
+#[derive(Debug, Default)]
+pub struct MemoryTable {
+    processes: Mutex<BTreeMap<u32, ProcessInfo>>,
+}
+
+impl MemoryTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Captures the current contents of another table
+    pub fn snapshot_of(table: &dyn ProcessTable) -> Self {
+        table.processes().into_iter().collect()
+    }
+
+    /// Replaces the contents with a fresh capture of another table
+    pub fn refresh_from(&self, table: &dyn ProcessTable) {
+        // Scan before locking so readers are not blocked meanwhile
+        let processes = table.processes();
+        *self.processes.lock().unwrap() =
+            processes.into_iter().map(|info| (info.pid, info)).collect();
+    }
+
+    /// Adds a process with a zero start time, replacing any existing entry
+    pub fn spawn(&self, pid: u32, parent_pid: u32) {
+        self.insert(ProcessInfo::new(pid, parent_pid, 0));
+    }
+
+    /// Adds a named process with a zero start time, replacing any existing entry
+    pub fn spawn_named(&self, pid: u32, parent_pid: u32, name: &str) {
+        self.insert(ProcessInfo::new(pid, parent_pid, 0).with_name(name));
+    }
+
+    pub fn insert(&self, info: ProcessInfo) {
+        self.processes.lock().unwrap().insert(info.pid, info);
+    }
+
+    /// Removes a process, returning whether it existed
+    pub fn remove(&self, pid: u32) -> bool {
+        self.processes.lock().unwrap().remove(&pid).is_some()
+    }
+}
+
+impl FromIterator<ProcessInfo> for MemoryTable {
+    fn from_iter<I: IntoIterator<Item = ProcessInfo>>(iter: I) -> Self {
+        let processes = iter.into_iter().map(|info| (info.pid, info)).collect();
+        Self {
+            processes: Mutex::new(processes),
+        }
+    }
+}
+
+impl ProcessTable for MemoryTable {
+    fn processes(&self) -> Vec<ProcessInfo> {
+        self.processes.lock().unwrap().values().cloned().collect()
+    }
+
+    fn process(&self, pid: u32) -> Option<ProcessInfo> {
+        self.processes.lock().unwrap().get(&pid).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_memory_table_lookup() {
+        let table = MemoryTable::new();
+        table.spawn(10, 1);
+        table.insert(ProcessInfo::new(11, 10, 1_700_000_000));
+
+        assert!(table.is_alive(10));
+        assert_eq!(table.parent_pid(11), Some(10));
+        assert_eq!(table.start_time(11), Some(1_700_000_000));
+
+        assert!(table.remove(10));
+        assert!(!table.is_alive(10));
+        assert_eq!(table.parent_pid(10), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_stat_with_tricky_name() {
+        let stat = "4242 (my) (proc) S 1 4242 4242 0 -1 4194560 0 0 0 0 0 0 0 0 20 0 1 0 500 0 0";
+        let info = parse_stat(stat, 1_000, 100).unwrap();
+        assert_eq!(info.pid, 4242);
+        assert_eq!(info.parent_pid, 1);
+        assert_eq!(info.start_time, 1_005);
+        assert_eq!(info.name, "my) (proc");
+        assert_eq!(info.state, Some('S'));
+        assert_eq!((info.pgid, info.sid), (Some(4242), Some(4242)));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_native_table_reads_procfs() {
+        let me = native_table().process(std::process::id()).unwrap();
+        assert!(me.state.is_some());
+        assert!(me.pgid.is_some() && me.sid.is_some());
+        assert!(me.start_time > 0);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_procfs_table_sees_current_process() {
+        let table = ProcfsTable::new();
+        let me = std::process::id();
+
+        assert!(table.is_alive(me));
+        assert_eq!(
+            table.parent_pid(me),
+            Some(std::os::unix::process::parent_id())
+        );
+        assert!(table.processes().iter().any(|p| p.pid == me));
+        assert_eq!(
+            table.process(me).and_then(|p| p.uid),
+            Some(unsafe { libc::geteuid() })
+        );
+    }
+}
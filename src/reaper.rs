@@ -0,0 +1,610 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use crate::process_table::{MemoryTable, ProcessTable};
+
+#[cfg(target_os = "linux")]
+use std::collections::HashMap;
+#[cfg(target_os = "linux")]
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd};
+#[cfg(target_os = "linux")]
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use nix::{sys::signal, unistd::Pid as NixPid};
+
+/// A termination request, independent of how it is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum KillSignal {
+    /// Polite request to exit (SIGTERM)
+    Term,
+    /// Immediate, uncatchable termination (SIGKILL)
+    Kill,
+    /// Interrupt, as from Ctrl-C (SIGINT)
+    Int,
+    /// Hangup (SIGHUP)
+    Hup,
+    /// Quit, usually with a core dump (SIGQUIT)
+    Quit,
+    /// User-defined signal 1 (SIGUSR1)
+    Usr1,
+    /// User-defined signal 2 (SIGUSR2)
+    Usr2,
+}
+
+impl KillSignal {
+    pub fn from_force(force: bool) -> Self {
+        if force {
+            KillSignal::Kill
+        } else {
+            KillSignal::Term
+        }
+    }
+
+    pub fn is_force(self) -> bool {
+        self == KillSignal::Kill
+    }
+
+    #[cfg(unix)]
+    pub fn to_nix(self) -> signal::Signal {
+        match self {
+            KillSignal::Term => signal::Signal::SIGTERM,
+            KillSignal::Kill => signal::Signal::SIGKILL,
+            KillSignal::Int => signal::Signal::SIGINT,
+            KillSignal::Hup => signal::Signal::SIGHUP,
+            KillSignal::Quit => signal::Signal::SIGQUIT,
+            KillSignal::Usr1 => signal::Signal::SIGUSR1,
+            KillSignal::Usr2 => signal::Signal::SIGUSR2,
+        }
+    }
+}
+
+impl fmt::Display for KillSignal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            KillSignal::Term => "SIGTERM",
+            KillSignal::Kill => "SIGKILL",
+            KillSignal::Int => "SIGINT",
+            KillSignal::Hup => "SIGHUP",
+            KillSignal::Quit => "SIGQUIT",
+            KillSignal::Usr1 => "SIGUSR1",
+            KillSignal::Usr2 => "SIGUSR2",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl FromStr for KillSignal {
+    type Err = String;
+
+    /// Parses signal names case-insensitively, with or without the `SIG` prefix
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_ascii_uppercase();
+        match upper.strip_prefix("SIG").unwrap_or(&upper) {
+            "TERM" => Ok(KillSignal::Term),
+            "KILL" => Ok(KillSignal::Kill),
+            "INT" => Ok(KillSignal::Int),
+            "HUP" => Ok(KillSignal::Hup),
+            "QUIT" => Ok(KillSignal::Quit),
+            "USR1" => Ok(KillSignal::Usr1),
+            "USR2" => Ok(KillSignal::Usr2),
+            _ => Err(format!("Invalid signal: {}", s.trim())),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for KillSignal {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl serde::Serialize for KillSignal {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Delivers termination requests to processes
+///
+/// Implementations decide *how* a process is asked to go away: POSIX signals,
+/// pidfds, cgroups, or something entirely external such as a container runtime.
+pub trait Reaper: Send + Sync {
+    /// Short name used in logs
+    fn name(&self) -> &str;
+
+    /// Delivers a signal to a single process
+    fn signal(&self, pid: u32, signal: KillSignal) -> Result<(), String>;
+
+    /// Delivers a signal to the whole group rooted at `root_pid`
+    ///
+    /// Returns an error when group delivery is unsupported or failed, in which
+    /// case callers fall back to signalling each process of the tree.
+    fn signal_group(&self, root_pid: u32, signal: KillSignal) -> Result<(), String> {
+        let _ = signal;
+        Err(format!(
+            "{} does not support group delivery (PID {})",
+            self.name(),
+            root_pid
+        ))
+    }
+
+    /// Processes a [`Reaper::signal_group`] on `root_pid` would reach right now
+    ///
+    /// Each of them is checked against the safety rules before a group
+    /// delivery. Defaults to the process group `root_pid` leads.
+    fn group_members(&self, table: &dyn ProcessTable, root_pid: u32) -> Result<Vec<u32>, String> {
+        Ok(process_group_members(table, root_pid))
+    }
+
+    /// Called when a contract records `pid` as a target or descendant, with
+    /// the start time it was recorded with; called again on every tick
+    ///
+    /// Reapers that hold a handle on each process open it here, so later
+    /// signals reach the recorded process even if its PID is recycled.
+    fn pin(&self, table: &dyn ProcessTable, pid: u32, start_time: u64) {
+        let _ = (table, pid, start_time);
+    }
+}
+
+/// `root_pid` and every process whose process group it leads
+pub fn process_group_members(table: &dyn ProcessTable, root_pid: u32) -> Vec<u32> {
+    let mut members: Vec<u32> = table
+        .processes()
+        .iter()
+        .filter(|p| p.pid != root_pid && p.pgid == Some(root_pid))
+        .map(|p| p.pid)
+        .collect();
+    members.insert(0, root_pid);
+    members
+}
+
+/// Delivers POSIX signals with `kill(2)`, or uses `taskkill` on Windows
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SignalReaper;
+
+impl Reaper for SignalReaper {
+    fn name(&self) -> &str {
+        "signal"
+    }
+
+    fn signal(&self, pid: u32, signal: KillSignal) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            signal::kill(NixPid::from_raw(pid as i32), signal.to_nix())
+                .map_err(|e| format!("{:?}", e))
+        }
+
+        #[cfg(windows)]
+        {
+            taskkill(pid, signal.is_force(), false)
+        }
+    }
+
+    fn signal_group(&self, root_pid: u32, signal: KillSignal) -> Result<(), String> {
+        #[cfg(unix)]
+        {
+            let pgid = -(root_pid as i32);
+            signal::kill(NixPid::from_raw(pgid), signal.to_nix()).map_err(|e| format!("{:?}", e))
+        }
+
+        #[cfg(windows)]
+        {
+            taskkill(root_pid, signal.is_force(), true)
+        }
+    }
+
+    fn group_members(&self, table: &dyn ProcessTable, root_pid: u32) -> Result<Vec<u32>, String> {
+        #[cfg(unix)]
+        {
+            Ok(process_group_members(table, root_pid))
+        }
+
+        // `taskkill /T` reaches the whole tree
+        #[cfg(windows)]
+        {
+            Ok(crate::build_process_tree_with(table, root_pid)
+                .into_keys()
+                .collect())
+        }
+    }
+}
+
+#[cfg(windows)]
+fn taskkill(pid: u32, force: bool, tree: bool) -> Result<(), String> {
+    let mut cmd = std::process::Command::new("taskkill");
+    cmd.arg("/PID").arg(pid.to_string());
+
+    if tree {
+        cmd.arg("/T"); // /T kills child processes
+    }
+    if force {
+        cmd.arg("/F"); // Force kill
+    }
+
+    match cmd.output() {
+        Ok(output) if output.status.success() => Ok(()),
+        Ok(output) => Err(format!("taskkill status: {}", output.status)),
+        Err(e) => Err(format!("failed to execute taskkill: {:?}", e)),
+    }
+}
+
+/// Delivers signals through `pidfd_send_signal(2)`
+///
+/// The pidfd of each process is opened when its contract records it (see
+/// [`Reaper::pin`]) and kept with its recorded start time, so a signal can
+/// never reach a process that took over a recycled PID: once the recorded
+/// process is gone, signalling it fails. A pidfd is only kept if the process
+/// still has the recorded start time once it is open. PIDs that were never
+/// recorded are opened right before the signal is sent. Descriptors stay open
+/// until the reaper is dropped.
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default)]
+pub struct PidfdReaper {
+    held: Mutex<HashMap<u32, Pinned>>,
+}
+
+/// A recorded process; `fd` is `None` once it was found gone
+#[cfg(target_os = "linux")]
+#[derive(Debug)]
+struct Pinned {
+    start_time: u64,
+    fd: Option<OwnedFd>,
+}
+
+#[cfg(target_os = "linux")]
+impl PidfdReaper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(pid: u32) -> std::io::Result<OwnedFd> {
+        let fd = unsafe { libc::syscall(libc::SYS_pidfd_open, pid as libc::pid_t, 0) };
+        if fd < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // pidfd_open returned a fresh descriptor that nothing else owns
+        Ok(unsafe { OwnedFd::from_raw_fd(fd as RawFd) })
+    }
+
+    fn send(fd: &OwnedFd, signal: KillSignal) -> Result<(), String> {
+        let result = unsafe {
+            libc::syscall(
+                libc::SYS_pidfd_send_signal,
+                fd.as_raw_fd(),
+                signal.to_nix() as libc::c_int,
+                std::ptr::null::<libc::siginfo_t>(),
+                0,
+            )
+        };
+        if result < 0 {
+            Err(format!(
+                "pidfd_send_signal failed: {}",
+                std::io::Error::last_os_error()
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Reaper for PidfdReaper {
+    fn name(&self) -> &str {
+        "pidfd"
+    }
+
+    fn signal(&self, pid: u32, signal: KillSignal) -> Result<(), String> {
+        match self.held.lock().unwrap().get(&pid) {
+            Some(Pinned { fd: Some(fd), .. }) => return Self::send(fd, signal),
+            Some(Pinned { fd: None, .. }) => {
+                return Err(format!("recorded process with PID {} is gone", pid))
+            }
+            None => {}
+        }
+        let fd = Self::open(pid).map_err(|e| format!("pidfd_open failed: {}", e))?;
+        Self::send(&fd, signal)
+    }
+
+    fn pin(&self, table: &dyn ProcessTable, pid: u32, start_time: u64) {
+        let mut held = self.held.lock().unwrap();
+        if held.get(&pid).is_some_and(|p| p.start_time == start_time) {
+            return;
+        }
+        let fd = match Self::open(pid) {
+            // Checked after opening: a PID recycled before that shows another start time
+            Ok(fd) if table.start_time(pid) == Some(start_time) => Some(fd),
+            Ok(_) => None,
+            Err(e) if e.raw_os_error() == Some(libc::ESRCH) => None,
+            // Without pidfds (e.g. before Linux 5.3) signals fall back to opening them late
+            Err(_) => return,
+        };
+        held.insert(pid, Pinned { start_time, fd });
+    }
+}
+
+/// Uses a cgroup v2 directory as the group: `cgroup.kill` for SIGKILL and
+/// every member of `cgroup.procs` for other signals
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone)]
+pub struct CgroupReaper {
+    cgroup: PathBuf,
+}
+
+#[cfg(target_os = "linux")]
+impl CgroupReaper {
+    /// Creates a reaper for the cgroup at the given path (e.g. `/sys/fs/cgroup/my-app`)
+    pub fn new(cgroup: impl Into<PathBuf>) -> Self {
+        Self {
+            cgroup: cgroup.into(),
+        }
+    }
+
+    fn members(&self) -> Result<Vec<u32>, String> {
+        let procs = std::fs::read_to_string(self.cgroup.join("cgroup.procs"))
+            .map_err(|e| format!("failed to read cgroup.procs: {}", e))?;
+        Ok(procs
+            .lines()
+            .filter_map(|line| line.trim().parse().ok())
+            .collect())
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Reaper for CgroupReaper {
+    fn name(&self) -> &str {
+        "cgroup"
+    }
+
+    fn signal(&self, pid: u32, signal: KillSignal) -> Result<(), String> {
+        SignalReaper.signal(pid, signal)
+    }
+
+    fn signal_group(&self, root_pid: u32, signal: KillSignal) -> Result<(), String> {
+        let members = self.members()?;
+        if !members.contains(&root_pid) {
+            return Err(format!(
+                "PID {} is not a member of cgroup {}",
+                root_pid,
+                self.cgroup.display()
+            ));
+        }
+
+        if signal.is_force() {
+            return std::fs::write(self.cgroup.join("cgroup.kill"), "1")
+                .map_err(|e| format!("failed to write cgroup.kill: {}", e));
+        }
+
+        for pid in members {
+            SignalReaper.signal(pid, signal)?;
+        }
+        Ok(())
+    }
+
+    fn group_members(&self, _table: &dyn ProcessTable, _root_pid: u32) -> Result<Vec<u32>, String> {
+        self.members()
+    }
+}
+
+/// A single delivery recorded by [`RecordingReaper`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReapCall {
+    pub pid: u32,
+    pub signal: KillSignal,
+    pub group: bool,
+}
+
+/// Test double that records every delivery instead of signalling anything
+///
+/// When attached to a [`MemoryTable`], signalled processes are removed from it
+/// so liveness checks observe the "kill". Stubborn PIDs only die on SIGKILL;
+/// unkillable PIDs never die.
+#[derive(Debug, Default)]
+pub struct RecordingReaper {
+    calls: Mutex<Vec<ReapCall>>,
+    table: Option<Arc<MemoryTable>>,
+    stubborn: HashSet<u32>,
+    unkillable: HashSet<u32>,
+    group_delivery: bool,
+}
+
+impl RecordingReaper {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Removes signalled processes from the given table
+    pub fn with_table(mut self, table: Arc<MemoryTable>) -> Self {
+        self.table = Some(table);
+        self
+    }
+
+    /// Marks a PID that ignores everything but SIGKILL
+    pub fn with_stubborn(mut self, pid: u32) -> Self {
+        self.stubborn.insert(pid);
+        self
+    }
+
+    /// Marks a PID that survives every signal, like a process stuck in `D` state
+    pub fn with_unkillable(mut self, pid: u32) -> Self {
+        self.unkillable.insert(pid);
+        self
+    }
+
+    /// Accepts group deliveries instead of forcing the per-process fallback
+    pub fn with_group_delivery(mut self) -> Self {
+        self.group_delivery = true;
+        self
+    }
+
+    /// Returns every delivery recorded so far, in order
+    pub fn calls(&self) -> Vec<ReapCall> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    fn record(&self, pid: u32, signal: KillSignal, group: bool) {
+        self.calls
+            .lock()
+            .unwrap()
+            .push(ReapCall { pid, signal, group });
+    }
+
+    fn deliver(&self, pid: u32, signal: KillSignal) {
+        if let Some(table) = &self.table {
+            if self.unkillable.contains(&pid) {
+                return;
+            }
+            if signal.is_force() || !self.stubborn.contains(&pid) {
+                table.remove(pid);
+            }
+        }
+    }
+}
+
+impl Reaper for RecordingReaper {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    fn signal(&self, pid: u32, signal: KillSignal) -> Result<(), String> {
+        self.record(pid, signal, false);
+        if let Some(table) = &self.table {
+            if !table.is_alive(pid) {
+                return Err("ESRCH".to_string());
+            }
+        }
+        self.deliver(pid, signal);
+        Ok(())
+    }
+
+    fn signal_group(&self, root_pid: u32, signal: KillSignal) -> Result<(), String> {
+        self.record(root_pid, signal, true);
+        if !self.group_delivery {
+            return Err("EPERM".to_string());
+        }
+
+        if let Some(table) = &self.table {
+            let tree = crate::build_process_tree_with(table.as_ref(), root_pid);
+            for pid in tree.keys() {
+                self.deliver(*pid, signal);
+            }
+        }
+        Ok(())
+    }
+
+    /// The tree of `root_pid`, which is what its group deliveries reach
+    fn group_members(&self, table: &dyn ProcessTable, root_pid: u32) -> Result<Vec<u32>, String> {
+        Ok(crate::build_process_tree_with(table, root_pid)
+            .into_keys()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kill_signal() {
+        assert_eq!("TERM".parse(), Ok(KillSignal::Term));
+        assert_eq!("sigkill".parse(), Ok(KillSignal::Kill));
+        assert_eq!(" Int ".parse(), Ok(KillSignal::Int));
+        assert_eq!(
+            "STOP".parse::<KillSignal>(),
+            Err("Invalid signal: STOP".to_string())
+        );
+    }
+
+    #[test]
+    fn test_recording_reaper_removes_from_table() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 1);
+        table.spawn(11, 1);
+        let reaper = RecordingReaper::new()
+            .with_table(table.clone())
+            .with_stubborn(11);
+
+        reaper.signal(10, KillSignal::Term).unwrap();
+        reaper.signal(11, KillSignal::Term).unwrap();
+        assert!(!table.is_alive(10));
+        assert!(table.is_alive(11));
+
+        reaper.signal(11, KillSignal::Kill).unwrap();
+        assert!(!table.is_alive(11));
+        assert!(reaper.signal(11, KillSignal::Kill).is_err());
+        assert_eq!(reaper.calls().len(), 4);
+    }
+
+    #[test]
+    fn test_recording_reaper_group_delivery() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 1);
+        table.spawn(11, 10);
+
+        let refusing = RecordingReaper::new().with_table(table.clone());
+        assert!(refusing.signal_group(10, KillSignal::Term).is_err());
+        assert!(table.is_alive(11));
+
+        let accepting = RecordingReaper::new()
+            .with_table(table.clone())
+            .with_group_delivery();
+        accepting.signal_group(10, KillSignal::Term).unwrap();
+        assert!(!table.is_alive(10));
+        assert!(!table.is_alive(11));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pidfd_reaper_terminates_child() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("100")
+            .spawn()
+            .expect("Failed to spawn sleep");
+
+        match PidfdReaper::new().signal(child.id(), KillSignal::Kill) {
+            Ok(()) => {
+                let status = child.wait().unwrap();
+                assert!(!status.success());
+            }
+            // Kernels older than 5.3 lack pidfd_open
+            Err(_) => {
+                child.kill().unwrap();
+                child.wait().unwrap();
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pidfd_reaper_signals_only_the_recorded_process() {
+        let mut child = std::process::Command::new("sleep")
+            .arg("100")
+            .spawn()
+            .expect("Failed to spawn sleep");
+        let pid = child.id();
+        let table = crate::process_table::native_table();
+        let start_time = table.start_time(pid).unwrap();
+        // Kernels older than 5.3 lack pidfd_open
+        if PidfdReaper::open(pid).is_err() {
+            child.kill().unwrap();
+            child.wait().unwrap();
+            return;
+        }
+
+        // Recorded with another start time: the PID now holds someone else
+        let stale = PidfdReaper::new();
+        stale.pin(table.as_ref(), pid, start_time + 1);
+        assert!(stale.signal(pid, KillSignal::Kill).is_err());
+        assert!(child.try_wait().unwrap().is_none());
+
+        let reaper = PidfdReaper::new();
+        reaper.pin(table.as_ref(), pid, start_time);
+        reaper.signal(pid, KillSignal::Kill).unwrap();
+        assert!(!child.wait().unwrap().success());
+        // The pidfd outlives the process instead of following its PID
+        assert!(reaper.signal(pid, KillSignal::Kill).is_err());
+    }
+}
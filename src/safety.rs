@@ -0,0 +1,262 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::process_table::ProcessTable;
+use crate::reaper::Reaper;
+
+/// A `--protect` entry: processes matching it are never signalled
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtectRule {
+    Pid(u32),
+    /// Executable name, as reported by the process table
+    Name(String),
+    /// Owner's user ID
+    Uid(u32),
+}
+
+impl FromStr for ProtectRule {
+    type Err = String;
+
+    /// Parses `pid:123`, `name:sshd` or `uid:0`
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("Invalid protect rule: {}", rule.trim());
+        match rule.trim().split_once(':') {
+            Some(("pid", pid)) => pid.parse().map(ProtectRule::Pid).map_err(|_| invalid()),
+            Some(("uid", uid)) => uid.parse().map(ProtectRule::Uid).map_err(|_| invalid()),
+            Some(("name", name)) if !name.is_empty() => Ok(ProtectRule::Name(name.to_string())),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for ProtectRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtectRule::Pid(pid) => write!(f, "pid:{}", pid),
+            ProtectRule::Name(name) => write!(f, "name:{}", name),
+            ProtectRule::Uid(uid) => write!(f, "uid:{}", uid),
+        }
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for ProtectRule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let rule = String::deserialize(deserializer)?;
+        rule.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Parses a comma-separated deny list such as `name:sshd,uid:0`
+pub fn parse_protect_rules(rules: &str) -> Result<Vec<ProtectRule>, String> {
+    rules
+        .split(',')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+/// Why `pid` must never be signalled, regardless of configuration
+///
+/// Covers PID 0, PID 1, process-wick itself and its ancestors.
+pub fn hard_refusal(table: &dyn ProcessTable, pid: u32) -> Option<String> {
+    match pid {
+        0 => Some("PID 0 is not a process".to_string()),
+        1 => Some("PID 1 is init".to_string()),
+        pid if pid == std::process::id() => Some("it is process-wick itself".to_string()),
+        pid if ancestors(table).contains(&pid) => {
+            Some("it is an ancestor of process-wick".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Why the process group led by `root_pid` must never be signalled
+pub fn group_refusal(root_pid: u32) -> Option<String> {
+    match root_pid {
+        0 => Some("PID 0 is not a process".to_string()),
+        1 => Some("PID 1 is init".to_string()),
+        pid if pid == std::process::id() => Some("it is process-wick itself".to_string()),
+        #[cfg(unix)]
+        pid if unsafe { libc::getpgid(0) } == pid as libc::pid_t => {
+            Some("its process group contains process-wick".to_string())
+        }
+        _ => None,
+    }
+}
+
+/// PIDs of process-wick's parent, grandparent, ... up to (excluding) PID 1
+fn ancestors(table: &dyn ProcessTable) -> HashSet<u32> {
+    let mut ancestors = HashSet::new();
+    let mut pid = std::process::id();
+    while let Some(parent) = table.parent_pid(pid) {
+        if parent <= 1 || !ancestors.insert(parent) {
+            break;
+        }
+        pid = parent;
+    }
+    ancestors
+}
+
+/// The hard-coded refusals plus a configurable deny list
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Safety {
+    rules: Vec<ProtectRule>,
+}
+
+impl Safety {
+    pub fn new(rules: Vec<ProtectRule>) -> Self {
+        Self { rules }
+    }
+
+    pub fn rules(&self) -> &[ProtectRule] {
+        &self.rules
+    }
+
+    /// Returns why `pid` may not be signalled, if it may not
+    pub fn check(&self, table: &dyn ProcessTable, pid: u32) -> Result<(), String> {
+        if let Some(reason) = hard_refusal(table, pid) {
+            return Err(reason);
+        }
+        let info = table.process(pid);
+        for rule in &self.rules {
+            let protected = match rule {
+                ProtectRule::Pid(protected) => *protected == pid,
+                ProtectRule::Name(name) => info.as_ref().is_some_and(|p| &p.name == name),
+                ProtectRule::Uid(uid) => info.as_ref().is_some_and(|p| p.uid == Some(*uid)),
+            };
+            if protected {
+                return Err(format!("it is protected by {}", rule));
+            }
+        }
+        Ok(())
+    }
+
+    /// Like [`Safety::check`], for signalling the whole group led by `root_pid`
+    ///
+    /// Every process the reaper's group delivery would reach has to pass
+    /// [`Safety::check`]; otherwise callers signal the PIDs one by one.
+    pub fn check_group(
+        &self,
+        table: &dyn ProcessTable,
+        reaper: &dyn Reaper,
+        root_pid: u32,
+    ) -> Result<(), String> {
+        if let Some(reason) = group_refusal(root_pid) {
+            return Err(reason);
+        }
+        self.check(table, root_pid)?;
+        for pid in reaper.group_members(table, root_pid)? {
+            if pid != root_pid {
+                self.check(table, pid)
+                    .map_err(|reason| format!("its group reaches PID {}, {}", pid, reason))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The owner of `pid` if it differs from the user process-wick runs as
+pub fn foreign_owner(table: &dyn ProcessTable, pid: u32) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        let me = unsafe { libc::geteuid() };
+        table
+            .process(pid)
+            .and_then(|p| p.uid)
+            .filter(|&uid| uid != me)
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = (table, pid);
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_table::{MemoryTable, ProcessInfo};
+    use crate::reaper::SignalReaper;
+
+    #[test]
+    fn test_parse_protect_rules() {
+        assert_eq!(
+            parse_protect_rules("name:sshd, uid:0,pid:42"),
+            Ok(vec![
+                ProtectRule::Name("sshd".to_string()),
+                ProtectRule::Uid(0),
+                ProtectRule::Pid(42),
+            ])
+        );
+        assert_eq!(
+            parse_protect_rules("user:root").unwrap_err(),
+            "Invalid protect rule: user:root"
+        );
+        assert!(parse_protect_rules("uid:root").is_err());
+        assert!(parse_protect_rules("name:").is_err());
+    }
+
+    #[test]
+    fn test_hard_refusals() {
+        let me = std::process::id();
+        let table = MemoryTable::new();
+        table.spawn(me, 500);
+        table.spawn(500, 400);
+        table.spawn(400, 1);
+        table.spawn(600, 1);
+
+        assert!(hard_refusal(&table, 0).is_some());
+        assert!(hard_refusal(&table, 1).is_some());
+        assert!(hard_refusal(&table, me).is_some());
+        assert!(hard_refusal(&table, 500).is_some());
+        assert!(hard_refusal(&table, 400).is_some());
+        assert_eq!(hard_refusal(&table, 600), None);
+    }
+
+    #[test]
+    fn test_protect_rules() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(10, 1, 0).with_name("sshd").with_uid(0));
+        table.insert(ProcessInfo::new(11, 1, 0).with_name("worker").with_uid(0));
+        table.insert(
+            ProcessInfo::new(12, 1, 0)
+                .with_name("worker")
+                .with_uid(1000),
+        );
+        let safety = Safety::new(parse_protect_rules("name:sshd,uid:0").unwrap());
+
+        assert_eq!(
+            safety.check(&table, 10),
+            Err("it is protected by name:sshd".to_string())
+        );
+        assert_eq!(
+            safety.check(&table, 11),
+            Err("it is protected by uid:0".to_string())
+        );
+        assert_eq!(safety.check(&table, 12), Ok(()));
+        assert!(safety.check_group(&table, &SignalReaper, 1).is_err());
+    }
+
+    #[test]
+    fn test_group_members_are_checked() {
+        let table = MemoryTable::new();
+        table.insert(ProcessInfo::new(20, 1, 0).with_group(20, 20));
+        table.insert(ProcessInfo::new(21, 20, 0).with_group(20, 20));
+        // Reparented away, but still in the group
+        table.insert(
+            ProcessInfo::new(22, 1, 0)
+                .with_group(20, 20)
+                .with_name("sshd"),
+        );
+        let safety = Safety::new(parse_protect_rules("name:sshd").unwrap());
+
+        assert_eq!(
+            safety.check_group(&table, &SignalReaper, 20),
+            Err("its group reaches PID 22, it is protected by name:sshd".to_string())
+        );
+        table.remove(22);
+        assert_eq!(safety.check_group(&table, &SignalReaper, 20), Ok(()));
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::TimeZone;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::discovery::{discover_with, Discovery, Origin};
+use crate::process_table::{MemoryTable, ProcessTable};
+use crate::{build_process_tree_with, ProcessNode};
+
+/// One process of a rendered tree, with its descendants nested below it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeEntry {
+    pub pid: u32,
+    pub ppid: u32,
+    pub pgid: Option<u32>,
+    pub sid: Option<u32>,
+    pub name: String,
+    pub state: Option<char>,
+    pub uid: Option<u32>,
+    pub user: Option<String>,
+    /// Start time in seconds since the Unix epoch
+    pub start_time: u64,
+    /// How the process was tied to the root
+    pub found: Discovery,
+    pub children: Vec<TreeEntry>,
+}
+
+/// The tree under a PID, plus the processes outside it that share the root's
+/// session or process group
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeReport {
+    pub tree: TreeEntry,
+    pub origin: Option<Origin>,
+    pub related: Vec<TreeEntry>,
+}
+
+/// Discovers the tree under `root_pid` exactly as vengeance would
+///
+/// With `sweep`, the root's current session and process group stand in for
+/// the ones a contract sweeping them would record when it starts. Works on a
+/// single snapshot of `table`, so every entry is consistent with the tree
+/// shape. `users` maps user IDs to names.
+pub fn tree_report(
+    table: &dyn ProcessTable,
+    root_pid: u32,
+    users: &HashMap<u32, String>,
+    sweep: bool,
+) -> Result<TreeReport, String> {
+    let snapshot = MemoryTable::snapshot_of(table);
+    if !snapshot.is_alive(root_pid) {
+        return Err(format!("No process with PID {}", root_pid));
+    }
+    let origin = sweep.then(|| Origin::of(&snapshot, root_pid)).flatten();
+    let tree = build_process_tree_with(&snapshot, root_pid);
+    let related = discover_with(&snapshot, root_pid, origin.as_ref(), &BTreeMap::new())
+        .into_iter()
+        .filter(|(pid, _)| !tree.contains_key(pid))
+        .map(|(pid, how)| TreeEntry {
+            found: how,
+            ..entry(&snapshot, &HashMap::new(), pid, users)
+        })
+        .collect();
+    Ok(TreeReport {
+        tree: entry(&snapshot, &tree, root_pid, users),
+        origin,
+        related,
+    })
+}
+
+fn entry(
+    snapshot: &MemoryTable,
+    tree: &HashMap<u32, ProcessNode>,
+    pid: u32,
+    users: &HashMap<u32, String>,
+) -> TreeEntry {
+    let info = snapshot.process(pid);
+    let uid = info.as_ref().and_then(|p| p.uid);
+    TreeEntry {
+        pid,
+        ppid: info.as_ref().map_or(0, |p| p.parent_pid),
+        pgid: info.as_ref().and_then(|p| p.pgid),
+        sid: info.as_ref().and_then(|p| p.sid),
+        name: info.as_ref().map(|p| p.name.clone()).unwrap_or_default(),
+        state: info.as_ref().and_then(|p| p.state),
+        uid,
+        user: uid.and_then(|uid| users.get(&uid).cloned()),
+        start_time: info.as_ref().map_or(0, |p| p.start_time),
+        found: match tree.get(&pid) {
+            Some(node) if node.depth > 0 => Discovery::Descendant,
+            _ => Discovery::Target,
+        },
+        children: tree
+            .get(&pid)
+            .map(|node| node.children.as_slice())
+            .unwrap_or_default()
+            .iter()
+            .map(|&child| entry(snapshot, tree, child, users))
+            .collect(),
+    }
+}
+
+/// Names of the users known to the system, by user ID
+pub fn user_names() -> HashMap<u32, String> {
+    #[cfg(unix)]
+    {
+        sysinfo::Users::new_with_refreshed_list()
+            .iter()
+            .map(|user| (**user.id(), user.name().to_string()))
+            .collect()
+    }
+
+    #[cfg(not(unix))]
+    {
+        HashMap::new()
+    }
+}
+
+/// Renders the report as a table, one process per line, names indented by depth
+///
+/// Processes found through the session or process group follow the tree.
+pub fn render_tree(report: &TreeReport) -> String {
+    let mut out = format!(
+        "{:>7} {:>7} {:>7} {:>7} {:<5} {:<10} {:<19} {:<6} {}\n",
+        "PID", "PPID", "PGID", "SID", "STATE", "USER", "STARTED", "FOUND", "NAME"
+    );
+    render_entry(&report.tree, 0, &mut out);
+    for entry in &report.related {
+        render_entry(entry, 0, &mut out);
+    }
+    out
+}
+
+fn render_entry(entry: &TreeEntry, depth: usize, out: &mut String) {
+    let optional = |value: Option<u32>| value.map_or("-".to_string(), |v| v.to_string());
+    let user = entry
+        .user
+        .clone()
+        .or_else(|| entry.uid.map(|uid| uid.to_string()))
+        .unwrap_or_else(|| "-".to_string());
+    let started = chrono::Local
+        .timestamp_opt(entry.start_time as i64, 0)
+        .single()
+        .map_or("-".to_string(), |time| {
+            time.format("%Y-%m-%d %H:%M:%S").to_string()
+        });
+
+    out.push_str(&format!(
+        "{:>7} {:>7} {:>7} {:>7} {:<5} {:<10} {:<19} {:<6} {}{}\n",
+        entry.pid,
+        entry.ppid,
+        optional(entry.pgid),
+        optional(entry.sid),
+        entry.state.unwrap_or('?'),
+        user,
+        started,
+        entry.found.to_string(),
+        "  ".repeat(depth),
+        entry.name
+    ));
+    for child in &entry.children {
+        render_entry(child, depth + 1, out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_table::ProcessInfo;
+
+    #[test]
+    fn test_tree_report() {
+        let table = MemoryTable::new();
+        table.insert(
+            ProcessInfo::new(100, 1, 0)
+                .with_name("server")
+                .with_uid(1000)
+                .with_group(100, 100)
+                .with_state('S'),
+        );
+        table.spawn_named(102, 100, "worker");
+        table.spawn_named(101, 100, "worker");
+        table.spawn_named(103, 101, "helper");
+        table.spawn_named(200, 1, "unrelated");
+        table.insert(
+            ProcessInfo::new(300, 1, 0)
+                .with_name("daemonized")
+                .with_group(300, 100),
+        );
+        let users = HashMap::from([(1000, "alice".to_string())]);
+
+        let report = tree_report(&table, 100, &users, true).unwrap();
+        let root = &report.tree;
+        assert_eq!(root.user.as_deref(), Some("alice"));
+        assert_eq!((root.pgid, root.sid), (Some(100), Some(100)));
+        assert_eq!(
+            root.children.iter().map(|c| c.pid).collect::<Vec<_>>(),
+            vec![101, 102]
+        );
+        assert_eq!(root.children[0].children[0].name, "helper");
+        assert_eq!(root.children[0].children[0].found, Discovery::Descendant);
+        assert_eq!(
+            report.related.iter().map(|e| e.pid).collect::<Vec<_>>(),
+            vec![300]
+        );
+
+        let rendered = render_tree(&report);
+        let names: Vec<&str> = rendered
+            .lines()
+            .skip(1)
+            .map(|line| line.split_at(69).1)
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                "target server",
+                "child    worker",
+                "child      helper",
+                "child    worker",
+                "sid    daemonized",
+            ]
+        );
+
+        // Without the sweep, only the tree itself
+        let report = tree_report(&table, 100, &users, false).unwrap();
+        assert!(report.related.is_empty());
+
+        assert_eq!(
+            tree_report(&table, 999, &users, true).unwrap_err(),
+            "No process with PID 999"
+        );
+    }
+}
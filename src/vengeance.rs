@@ -0,0 +1,1365 @@
+use log::{info, warn};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::discovery::{discover_with, Discovery, Lineage, Origin};
+use crate::events::{EventBus, WickEvent};
+use crate::graceful::GracefulStop;
+use crate::proc_events::ProcEvent;
+use crate::process_table::{native_table, ProcessTable};
+use crate::reaper::{KillSignal, Reaper, SignalReaper};
+use crate::safety::Safety;
+use crate::{send_signal_to_pids_with, try_kill_process_group_with};
+
+/// How long to wait for force-killed processes to disappear from the table
+const EXIT_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(1);
+const EXIT_CONFIRMATION_POLL: Duration = Duration::from_millis(100);
+
+/// How long a wave may linger after its ladder before what is left of it is
+/// force-killed again
+const WAVE_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How many times a lingering wave is force-killed again before the next wave
+/// starts without it
+const WAVE_CONFIRMATION_ATTEMPTS: u32 = 3;
+
+/// Orders targets into shutdown waves
+///
+/// `after` maps a target to the targets that must be dead before it is
+/// signalled. Dependencies on PIDs outside `targets` are ignored. Every wave is
+/// sorted; a cycle is an error naming the targets caught in it.
+pub fn shutdown_waves(
+    targets: &[u32],
+    after: &HashMap<u32, BTreeSet<u32>>,
+) -> Result<Vec<Vec<u32>>, String> {
+    let all: BTreeSet<u32> = targets.iter().copied().collect();
+    let mut pending: BTreeMap<u32, BTreeSet<u32>> = all
+        .iter()
+        .map(|&pid| {
+            let deps = after
+                .get(&pid)
+                .map(|deps| deps.intersection(&all).copied().collect())
+                .unwrap_or_default();
+            (pid, deps)
+        })
+        .collect();
+
+    let mut waves = Vec::new();
+    while !pending.is_empty() {
+        let wave: Vec<u32> = pending
+            .iter()
+            .filter(|(_, deps)| deps.is_empty())
+            .map(|(&pid, _)| pid)
+            .collect();
+        if wave.is_empty() {
+            let stuck: Vec<u32> = pending.keys().copied().collect();
+            return Err(format!("Dependency cycle between targets: {:?}", stuck));
+        }
+        for pid in &wave {
+            pending.remove(pid);
+        }
+        for deps in pending.values_mut() {
+            for pid in &wave {
+                deps.remove(pid);
+            }
+        }
+        waves.push(wave);
+    }
+    Ok(waves)
+}
+
+/// One step of a signal ladder: send `signal`, then give processes up to
+/// `wait` to exit before the next step
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rung {
+    pub signal: KillSignal,
+    pub wait: Duration,
+}
+
+impl Rung {
+    pub fn new(signal: KillSignal, wait: Duration) -> Self {
+        Self { signal, wait }
+    }
+}
+
+/// The classic ladder: SIGTERM, wait `delay`, then SIGKILL
+pub fn default_ladder(delay: Duration) -> Vec<Rung> {
+    vec![
+        Rung::new(KillSignal::Term, delay),
+        Rung::new(KillSignal::Kill, Duration::ZERO),
+    ]
+}
+
+/// A copy of `ladder` with its first signal and wait replaced where given
+pub fn customize_ladder(
+    ladder: &[Rung],
+    signal: Option<KillSignal>,
+    wait: Option<Duration>,
+) -> Vec<Rung> {
+    let mut ladder = ladder.to_vec();
+    if let Some(first) = ladder.first_mut() {
+        first.signal = signal.unwrap_or(first.signal);
+        first.wait = wait.unwrap_or(first.wait);
+    }
+    ladder
+}
+
+/// Renders a ladder as `SIGTERM +5s, SIGKILL +0s`
+pub fn describe_ladder(ladder: &[Rung]) -> String {
+    ladder
+        .iter()
+        .map(|rung| format!("{} +{}s", rung.signal, rung.wait.as_secs()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether a process was found outside the target's current tree, so the
+/// target's group signal may not reach it
+fn is_related(how: Discovery) -> bool {
+    matches!(
+        how,
+        Discovery::Session | Discovery::ProcessGroup | Discovery::Orphan
+    )
+}
+
+/// Renders discovered PIDs as `[100, 101 (child), 150 (sid)]`
+fn describe_found(found: &[(u32, Discovery)]) -> String {
+    let pids: Vec<String> = found
+        .iter()
+        .map(|(pid, how)| match how {
+            Discovery::Target => pid.to_string(),
+            how => format!("{} ({})", pid, how),
+        })
+        .collect();
+    format!("[{}]", pids.join(", "))
+}
+
+/// What a planned step does to its PID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlannedAction {
+    /// Run the target's graceful-stop action in place of the first rung
+    GracefulStop(GracefulStop),
+    /// Signal the process group led by the PID
+    GroupSignal(KillSignal),
+    /// Signal the PID alone, used when its target's group signal fails
+    Signal(KillSignal),
+    /// Leave the PID alone for the given reason
+    Refused(String),
+}
+
+/// One step of a dry run: what would happen to `pid`, `at` after the dog died
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedStep {
+    pub at: Duration,
+    /// 1-based shutdown wave
+    pub wave: usize,
+    /// The target whose tree `pid` belongs to
+    pub target: u32,
+    pub pid: u32,
+    /// How `pid` was tied to the target
+    pub found: Discovery,
+    pub action: PlannedAction,
+}
+
+impl fmt::Display for PlannedStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "T+{}s wave {}: ", self.at.as_secs(), self.wave)?;
+        match &self.action {
+            PlannedAction::GracefulStop(stop) => {
+                write!(f, "ask PID {} to stop with {}", self.pid, stop)
+            }
+            PlannedAction::GroupSignal(signal) => {
+                write!(f, "{} → process group of PID {}", signal, self.pid)
+            }
+            PlannedAction::Signal(signal) => match self.found {
+                Discovery::Target => write!(
+                    f,
+                    "{} → PID {} (if the group signal fails)",
+                    signal, self.pid
+                ),
+                Discovery::Descendant => write!(
+                    f,
+                    "{} → PID {} (child of target {}, if the group signal fails)",
+                    signal, self.pid, self.target
+                ),
+                Discovery::Session => write!(
+                    f,
+                    "{} → PID {} (same session as target {})",
+                    signal, self.pid, self.target
+                ),
+                Discovery::ProcessGroup => write!(
+                    f,
+                    "{} → PID {} (same process group as target {})",
+                    signal, self.pid, self.target
+                ),
+                Discovery::Orphan => write!(
+                    f,
+                    "{} → PID {} (former child of target {})",
+                    signal, self.pid, self.target
+                ),
+            },
+            PlannedAction::Refused(reason) => {
+                write!(f, "skip PID {}: {}", self.pid, reason)
+            }
+        }
+    }
+}
+
+/// The graceful→force termination sequence run once the dog dies
+///
+/// Every rung of the ladder first asks each target to leave as a group;
+/// targets whose group could not be signalled get their process tree (refreshed
+/// on every rung) signalled one PID at a time. Processes sharing a target's
+/// recorded session or process group, and former descendants that lost their
+/// parent, are signalled one at a time as well, whether or not the group
+/// signal worked. By default the ladder is
+/// SIGTERM, the vengeance delay, then SIGKILL. Targets with a graceful-stop
+/// action run it in place of the first rung.
+#[derive(Clone)]
+pub struct Vengeance {
+    table: Arc<dyn ProcessTable>,
+    reaper: Arc<dyn Reaper>,
+    ladder: Vec<Rung>,
+    target_ladders: HashMap<u32, Vec<Rung>>,
+    after: HashMap<u32, BTreeSet<u32>>,
+    stops: HashMap<u32, GracefulStop>,
+    origins: HashMap<u32, Origin>,
+    lineage: Arc<Lineage>,
+    safety: Safety,
+    events: EventBus,
+    wave_timeout: Duration,
+    wave_attempts: u32,
+}
+
+impl Vengeance {
+    /// Creates a sequence backed by the native process table (`/proc` on Linux,
+    /// sysinfo elsewhere) and POSIX signals (or `taskkill`)
+    pub fn new(delay: Duration) -> Self {
+        Self {
+            table: native_table(),
+            reaper: Arc::new(SignalReaper),
+            ladder: default_ladder(delay),
+            target_ladders: HashMap::new(),
+            after: HashMap::new(),
+            stops: HashMap::new(),
+            origins: HashMap::new(),
+            lineage: Arc::new(Lineage::new()),
+            safety: Safety::default(),
+            events: EventBus::new(),
+            wave_timeout: WAVE_CONFIRMATION_TIMEOUT,
+            wave_attempts: WAVE_CONFIRMATION_ATTEMPTS,
+        }
+    }
+
+    pub fn with_table(mut self, table: Arc<dyn ProcessTable>) -> Self {
+        self.table = table;
+        self
+    }
+
+    pub fn with_reaper(mut self, reaper: Arc<dyn Reaper>) -> Self {
+        self.reaper = reaper;
+        self
+    }
+
+    /// Replaces the default SIGTERM→SIGKILL ladder
+    pub fn with_ladder(mut self, ladder: Vec<Rung>) -> Self {
+        self.ladder = ladder;
+        self
+    }
+
+    /// Gives `pid` its own ladder instead of the shared one
+    pub fn with_target_ladder(mut self, pid: u32, ladder: Vec<Rung>) -> Self {
+        self.target_ladders.insert(pid, ladder);
+        self
+    }
+
+    /// Signals `pid` only once `after` is confirmed dead
+    ///
+    /// Fails if the dependency closes a cycle.
+    pub fn with_dependency(mut self, pid: u32, after: u32) -> Result<Self, String> {
+        self.after.entry(pid).or_default().insert(after);
+        let pids: Vec<u32> = self
+            .after
+            .iter()
+            .flat_map(|(&pid, after)| std::iter::once(pid).chain(after.iter().copied()))
+            .collect();
+        shutdown_waves(&pids, &self.after)?;
+        Ok(self)
+    }
+
+    /// How long a wave may linger after its ladder, and how many times what is
+    /// left of it is force-killed again, before the next wave starts anyway
+    pub fn with_wave_confirmation(mut self, timeout: Duration, attempts: u32) -> Self {
+        self.wave_timeout = timeout;
+        self.wave_attempts = attempts;
+        self
+    }
+
+    /// Asks `pid` to stop with an action instead of the first rung's signal
+    ///
+    /// The action gets the first rung's wait to succeed. If it fails, the
+    /// target gets that rung's signal after all.
+    pub fn with_graceful_stop(mut self, pid: u32, stop: GracefulStop) -> Self {
+        self.stops.insert(pid, stop);
+        self
+    }
+
+    /// Also takes down processes sharing the session or process group `pid` had
+    /// when the contract started, even if they are no longer its descendants
+    pub fn with_origin(mut self, pid: u32, origin: Origin) -> Self {
+        self.origins.insert(pid, origin);
+        self
+    }
+
+    /// Records the current descendants of the targets, so they are still taken
+    /// down if they lose their parent before vengeance
+    pub fn track(&self, targets: &[u32]) {
+        self.track_in(self.table.as_ref(), targets);
+    }
+
+    /// Like [`Vengeance::track`], reading the descendants from another table
+    ///
+    /// Every tracked process is also pinned with the reaper (see
+    /// [`Reaper::pin`]), which checks it against vengeance's own table.
+    pub fn track_in(&self, table: &dyn ProcessTable, targets: &[u32]) {
+        self.lineage.track(table, targets);
+        for (pid, start_time) in self.lineage.pinned() {
+            self.reaper.pin(self.table.as_ref(), pid, start_time);
+        }
+    }
+
+    /// Applies a fork or exit reported by the kernel to the targets' lineage
+    pub fn follow(&self, event: ProcEvent, targets: &[u32]) {
+        match event {
+            ProcEvent::Fork { parent, child } => {
+                self.lineage
+                    .record_fork(self.table.as_ref(), targets, parent, child);
+            }
+            ProcEvent::Exit { pid } => self.lineage.record_exit(pid),
+        }
+    }
+
+    /// Never signals processes matched by the given deny list
+    ///
+    /// PID 0, PID 1, process-wick and its ancestors are always refused.
+    pub fn with_safety(mut self, safety: Safety) -> Self {
+        self.safety = safety;
+        self
+    }
+
+    /// Emits events on the given bus instead of a private one
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
+    pub fn safety(&self) -> &Safety {
+        &self.safety
+    }
+
+    pub fn table(&self) -> &Arc<dyn ProcessTable> {
+        &self.table
+    }
+
+    pub fn reaper(&self) -> &Arc<dyn Reaper> {
+        &self.reaper
+    }
+
+    pub fn ladder(&self) -> &[Rung] {
+        &self.ladder
+    }
+
+    /// Total time the ladder waits between its rungs
+    pub fn delay(&self) -> Duration {
+        self.ladder.iter().map(|rung| rung.wait).sum()
+    }
+
+    /// Works out what [`Vengeance::execute`] would do right now, without sending anything
+    ///
+    /// Trees are built from the current process table. Times assume every wave
+    /// is gone by the end of its ladder; in a real run trees are refreshed on
+    /// every rung and a lingering wave delays the next one.
+    pub fn plan(&self, targets: &[u32]) -> Vec<PlannedStep> {
+        let mut steps = Vec::new();
+        let mut allowed = Vec::new();
+        for &pid in targets {
+            match self.safety.check(self.table.as_ref(), pid) {
+                Ok(()) => allowed.push(pid),
+                Err(reason) => steps.push(PlannedStep {
+                    at: Duration::ZERO,
+                    wave: 1,
+                    target: pid,
+                    pid,
+                    found: Discovery::Target,
+                    action: PlannedAction::Refused(reason),
+                }),
+            }
+        }
+
+        let waves = self.waves(&allowed);
+        let mut wave_start = Duration::ZERO;
+        for (index, wave) in waves.iter().enumerate() {
+            let mut wave_length = Duration::ZERO;
+            for &target in wave {
+                let ladder = self
+                    .target_ladders
+                    .get(&target)
+                    .map_or(self.ladder.as_slice(), Vec::as_slice);
+                let found = self.discover(target);
+
+                let mut at = wave_start;
+                for (rung_index, rung) in ladder.iter().enumerate() {
+                    let mut step = |pid, found, action| {
+                        steps.push(PlannedStep {
+                            at,
+                            wave: index + 1,
+                            target,
+                            pid,
+                            found,
+                            action,
+                        })
+                    };
+                    let stop = self.stops.get(&target);
+                    match stop {
+                        Some(stop) if rung_index == 0 && ladder.len() > 1 => step(
+                            target,
+                            Discovery::Target,
+                            PlannedAction::GracefulStop(stop.clone()),
+                        ),
+                        _ => {
+                            step(
+                                target,
+                                Discovery::Target,
+                                match self.safety.check_group(
+                                    self.table.as_ref(),
+                                    self.reaper.as_ref(),
+                                    target,
+                                ) {
+                                    Ok(()) => PlannedAction::GroupSignal(rung.signal),
+                                    Err(reason) => PlannedAction::Refused(reason),
+                                },
+                            );
+                            for &(pid, how) in &found {
+                                step(
+                                    pid,
+                                    how,
+                                    match self.safety.check(self.table.as_ref(), pid) {
+                                        Ok(()) => PlannedAction::Signal(rung.signal),
+                                        Err(reason) => PlannedAction::Refused(reason),
+                                    },
+                                );
+                            }
+                        }
+                    }
+                    at += rung.wait;
+                }
+                wave_length = wave_length.max(at - wave_start);
+            }
+            wave_start += wave_length;
+        }
+        steps.sort_by_key(|step| (step.at, step.wave));
+        steps
+    }
+
+    /// Runs the full sequence against the given targets
+    ///
+    /// Targets are taken down in dependency waves; a wave is only signalled
+    /// once the previous one is confirmed dead, trees included. Whatever
+    /// outlives a wave's ladder is force-killed again a few times; processes
+    /// that survive even that (stuck in `D` state, or refused with EPERM) are
+    /// reported in a [`WickEvent::GraceExpired`] and the next wave starts
+    /// anyway, so one unkillable process cannot keep every later target, and
+    /// the after-kill hooks, waiting forever. Within a wave, targets with
+    /// their own ladder run concurrently, each on its own schedule.
+    pub async fn execute(&self, targets: &[u32]) {
+        let targets: Vec<u32> = targets
+            .iter()
+            .copied()
+            .filter(|&pid| match self.safety.check(self.table.as_ref(), pid) {
+                Ok(()) => true,
+                Err(reason) => {
+                    warn!("🛡️ Refusing target PID {}: {}", pid, reason);
+                    self.events.emit(WickEvent::TargetRefused { pid, reason });
+                    false
+                }
+            })
+            .collect();
+        let targets = targets.as_slice();
+
+        let waves = self.waves(targets);
+        if waves.len() <= 1 {
+            self.execute_wave(targets).await;
+            return;
+        }
+
+        for (index, wave) in waves.iter().enumerate() {
+            info!("🌊 Wave {}/{}: {:?}", index + 1, waves.len(), wave);
+            self.events.emit(WickEvent::WaveStarted {
+                wave: index + 1,
+                pids: wave.clone(),
+            });
+            let mut signalled = self.snapshot(wave);
+            let survivors = self.execute_wave(wave).await;
+            signalled.extend(self.snapshot(&survivors));
+            self.confirm_wave(index + 1, wave, signalled).await;
+        }
+    }
+
+    /// The shutdown waves of `targets`
+    fn waves(&self, targets: &[u32]) -> Vec<Vec<u32>> {
+        match shutdown_waves(targets, &self.after) {
+            Ok(waves) => waves,
+            // with_dependency refuses cycles, but never leave a target standing over one
+            Err(e) => {
+                warn!("⚠️ {}, taking every target down in one wave", e);
+                vec![targets.to_vec()]
+            }
+        }
+    }
+
+    /// Waits until nothing the wave signalled is left, force-killing what
+    /// lingers, so dependents do not start while any of it can still be killed
+    ///
+    /// Gives up after the configured number of attempts, emitting
+    /// [`WickEvent::GraceExpired`] with the processes that are stuck.
+    async fn confirm_wave(&self, number: usize, wave: &[u32], mut left: BTreeMap<u32, u64>) {
+        for attempt in 0..=self.wave_attempts {
+            let deadline = tokio::time::Instant::now() + self.wave_timeout;
+            loop {
+                left.retain(|&pid, &mut start| self.lingers(pid, start));
+                if left.is_empty() {
+                    info!("✅ Wave {} confirmed dead", number);
+                    return;
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    break;
+                }
+                tokio::time::sleep(EXIT_CONFIRMATION_POLL).await;
+            }
+
+            // Whatever the targets spawned since is part of the wave too
+            let live_targets: Vec<u32> = wave
+                .iter()
+                .copied()
+                .filter(|pid| left.contains_key(pid))
+                .collect();
+            left.extend(self.snapshot(&live_targets));
+            let survivors: Vec<u32> = left.keys().copied().collect();
+            self.events.emit(WickEvent::GraceExpired {
+                survivors: survivors.clone(),
+            });
+            if attempt == self.wave_attempts {
+                warn!(
+                    "💀 Wave {} still has survivors after {} force kills: {:?}. Giving up on them and starting the next wave.",
+                    number, attempt, survivors
+                );
+                return;
+            }
+            warn!(
+                "⚠️ Wave {} not confirmed dead, survivors: {:?}. Force killing them before the next wave.",
+                number, survivors
+            );
+            self.signal_pids(&survivors, KillSignal::Kill);
+        }
+    }
+
+    /// The trees of `pids` as they are now, with each process's start time so
+    /// a recycled PID is not mistaken for a survivor; protected processes are
+    /// left out since they are never signalled
+    fn snapshot(&self, pids: &[u32]) -> BTreeMap<u32, u64> {
+        pids.iter()
+            .flat_map(|&pid| self.discover(pid))
+            .filter(|&(pid, _)| self.safety.check(self.table.as_ref(), pid).is_ok())
+            .filter_map(|(pid, _)| Some((pid, self.table.start_time(pid)?)))
+            .collect()
+    }
+
+    /// Whether the process that started at `start` still holds `pid`; zombies
+    /// are past signalling and do not count
+    fn lingers(&self, pid: u32, start: u64) -> bool {
+        self.table
+            .process(pid)
+            .is_some_and(|p| p.start_time == start && p.state != Some('Z'))
+    }
+
+    /// Runs the wave's ladders and returns the processes still alive after them
+    async fn execute_wave(&self, targets: &[u32]) -> Vec<u32> {
+        let mut schedules: Vec<(&[Rung], Vec<u32>)> = Vec::new();
+        for &pid in targets {
+            let ladder = self
+                .target_ladders
+                .get(&pid)
+                .map_or(self.ladder.as_slice(), Vec::as_slice);
+            match schedules.iter_mut().find(|(l, _)| *l == ladder) {
+                Some((_, pids)) => pids.push(pid),
+                None => schedules.push((ladder, vec![pid])),
+            }
+        }
+
+        if schedules.len() <= 1 {
+            let ladder = schedules
+                .first()
+                .map_or(self.ladder.as_slice(), |(l, _)| *l);
+            return self.run_ladder(targets, ladder).await;
+        }
+
+        let mut running = tokio::task::JoinSet::new();
+        for (ladder, pids) in schedules {
+            info!(
+                "⏱️ PIDs {:?} on their own schedule: {}",
+                pids,
+                describe_ladder(ladder)
+            );
+            let this = self.clone();
+            let ladder = ladder.to_vec();
+            running.spawn(async move { this.run_ladder(&pids, &ladder).await });
+        }
+        let mut survivors = Vec::new();
+        while let Some(joined) = running.join_next().await {
+            survivors.extend(joined.unwrap_or_default());
+        }
+        survivors
+    }
+
+    async fn run_ladder(&self, targets: &[u32], ladder: &[Rung]) -> Vec<u32> {
+        // Every PID signalled individually so far, preserved across rungs
+        let mut kill_list: Vec<u32> = Vec::new();
+        let mut watched: Vec<u32> = targets.to_vec();
+        let mut known: HashSet<u32> = watched.iter().copied().collect();
+
+        for (index, rung) in ladder.iter().enumerate() {
+            let first = index == 0;
+            let last = index + 1 == ladder.len();
+            let sig = rung.signal;
+
+            // Graceful-stop actions replace the first rung for their targets
+            let mut stopping = tokio::task::JoinSet::new();
+            if first && !last {
+                for &pid in targets {
+                    if let Some(stop) = self.stops.get(&pid).cloned() {
+                        let wait = rung.wait;
+                        stopping.spawn(async move { (pid, stop.run(pid, wait).await) });
+                    }
+                }
+            }
+
+            // Try group killing first for all targets
+            let mut signalled: Vec<(u32, bool)> = Vec::new();
+            for &pid in targets {
+                if first && !last && self.stops.contains_key(&pid) {
+                    continue;
+                }
+                if sig.is_force() {
+                    info!("⚠️ Forcefully attempting group kill for PID {}", pid);
+                } else {
+                    info!("⚠️ Attempting group kill for PID {} with {}", pid, sig);
+                }
+
+                let group_killed = self.signal_group(pid, sig);
+                if !group_killed {
+                    info!(
+                        "⚠️ Group kill failed for PID {}, will use individual process termination",
+                        pid
+                    );
+                }
+                signalled.push((pid, group_killed));
+            }
+
+            // Targets whose group could not be signalled need their whole tree
+            // signalled individually; processes tied to a target by session,
+            // process group or lineage may escape its group signal either way
+            let needing_discovery: Vec<(u32, bool)> = signalled
+                .into_iter()
+                .filter(|&(pid, group_killed)| !group_killed || self.may_have_relatives(pid))
+                .collect();
+            if !needing_discovery.is_empty() {
+                if first {
+                    info!("🔍 Building fresh process trees for individual termination");
+                } else {
+                    info!("🔍 Refreshing process trees to catch any new processes");
+                }
+
+                for (pid, group_killed) in needing_discovery {
+                    let found: Vec<(u32, Discovery)> = self
+                        .discover(pid)
+                        .into_iter()
+                        .filter(|&(_, how)| !group_killed || is_related(how))
+                        .collect();
+                    let pids_in_order: Vec<u32> = found.iter().map(|&(pid, _)| pid).collect();
+
+                    info!(
+                        "📋 PID {} has {} processes to signal: {}",
+                        pid,
+                        pids_in_order.len(),
+                        describe_found(&found)
+                    );
+                    self.events.emit(WickEvent::TreeDiscovered {
+                        target: pid,
+                        pids: pids_in_order.clone(),
+                        how: found.into_iter().collect(),
+                    });
+
+                    // Add any new PIDs, preserving the ones from earlier rungs
+                    for &tree_pid in &pids_in_order {
+                        if !kill_list.contains(&tree_pid) {
+                            if !first {
+                                info!("➕ Adding new PID {} to kill list", tree_pid);
+                            }
+                            kill_list.push(tree_pid);
+                        }
+                        if known.insert(tree_pid) {
+                            watched.push(tree_pid);
+                        }
+                    }
+                }
+
+                info!("🎯 Total PIDs to kill individually: {:?}", kill_list);
+            }
+
+            if !kill_list.is_empty() {
+                if sig.is_force() {
+                    info!("🔪 Force killing all processes in kill list");
+                } else {
+                    info!("⚠️ Sending {} to individual processes", sig);
+                }
+                self.signal_pids(&kill_list, sig);
+            }
+
+            if last {
+                break;
+            }
+
+            // Wait for graceful termination
+            info!(
+                "⏳ Waiting {} seconds for graceful termination...",
+                rung.wait.as_secs()
+            );
+            let deadline = tokio::time::Instant::now() + rung.wait;
+            while let Ok(Some(joined)) =
+                tokio::time::timeout_at(deadline, stopping.join_next()).await
+            {
+                let Ok((pid, result)) = joined else {
+                    continue;
+                };
+                self.events.emit(WickEvent::GracefulStopFinished {
+                    pid,
+                    error: result.as_ref().err().cloned(),
+                });
+                if result.is_err() {
+                    warn!("⚠️ Falling back to {} for PID {}", sig, pid);
+                    let group_killed = self.signal_group(pid, sig);
+                    if !group_killed || self.may_have_relatives(pid) {
+                        let pids_in_order: Vec<u32> = self
+                            .discover(pid)
+                            .into_iter()
+                            .filter(|&(_, how)| !group_killed || is_related(how))
+                            .map(|(pid, _)| pid)
+                            .collect();
+                        for &tree_pid in &pids_in_order {
+                            if !kill_list.contains(&tree_pid) {
+                                kill_list.push(tree_pid);
+                            }
+                            if known.insert(tree_pid) {
+                                watched.push(tree_pid);
+                            }
+                        }
+                        self.signal_pids(&pids_in_order, sig);
+                    }
+                }
+            }
+            tokio::time::sleep_until(deadline).await;
+
+            let survivors = self.confirm_exits(&mut watched);
+            if !survivors.is_empty() {
+                self.events.emit(WickEvent::GraceExpired { survivors });
+            }
+        }
+
+        // Give the kernel a moment to tear down the last rung's victims
+        let deadline = tokio::time::Instant::now() + EXIT_CONFIRMATION_TIMEOUT;
+        while !self.confirm_exits(&mut watched).is_empty() && tokio::time::Instant::now() < deadline
+        {
+            tokio::time::sleep(EXIT_CONFIRMATION_POLL).await;
+        }
+        watched
+    }
+
+    /// Whether processes outside the tree of `pid` may still belong to it
+    fn may_have_relatives(&self, pid: u32) -> bool {
+        self.origins.contains_key(&pid) || !self.lineage.members(pid).is_empty()
+    }
+
+    /// The tree of `pid` plus whatever shares its recorded session or group,
+    /// and its tracked former descendants
+    pub fn discover(&self, pid: u32) -> Vec<(u32, Discovery)> {
+        discover_with(
+            self.table.as_ref(),
+            pid,
+            self.origins.get(&pid),
+            &self.lineage.members(pid),
+        )
+    }
+
+    fn signal_group(&self, pid: u32, sig: KillSignal) -> bool {
+        let result = match self
+            .safety
+            .check_group(self.table.as_ref(), self.reaper.as_ref(), pid)
+        {
+            Ok(()) => try_kill_process_group_with(self.reaper.as_ref(), pid, sig),
+            Err(reason) => {
+                warn!(
+                    "🛡️ Refusing to send {} to process group of PID {}: {}",
+                    sig, pid, reason
+                );
+                Err(format!("Refused: {}", reason))
+            }
+        };
+        self.emit_delivery(pid, sig, true, result)
+    }
+
+    fn signal_pids(&self, pids: &[u32], sig: KillSignal) {
+        let mut allowed = Vec::new();
+        for &pid in pids {
+            match self.safety.check(self.table.as_ref(), pid) {
+                Ok(()) => allowed.push(pid),
+                Err(reason) => {
+                    warn!("🛡️ Refusing to send {} to PID {}: {}", sig, pid, reason);
+                    self.emit_delivery(pid, sig, false, Err(format!("Refused: {}", reason)));
+                }
+            }
+        }
+
+        let results =
+            send_signal_to_pids_with(self.table.as_ref(), self.reaper.as_ref(), &allowed, sig);
+        for (pid, result) in results {
+            self.emit_delivery(pid, sig, false, result);
+        }
+    }
+
+    fn emit_delivery(
+        &self,
+        pid: u32,
+        signal: KillSignal,
+        group: bool,
+        result: Result<(), String>,
+    ) -> bool {
+        match result {
+            Ok(()) => {
+                self.events
+                    .emit(WickEvent::SignalSent { pid, signal, group });
+                true
+            }
+            Err(error) => {
+                self.events.emit(WickEvent::SignalFailed {
+                    pid,
+                    signal,
+                    group,
+                    error,
+                });
+                false
+            }
+        }
+    }
+
+    /// Drops exited PIDs from `watched`, emitting an event for each,
+    /// and returns the ones still alive
+    fn confirm_exits(&self, watched: &mut Vec<u32>) -> Vec<u32> {
+        watched.retain(|&pid| {
+            let alive = self.table.is_alive(pid);
+            if !alive {
+                self.events.emit(WickEvent::ProcessExited { pid });
+            }
+            alive
+        });
+        watched.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process_table::{MemoryTable, ProcessInfo};
+    use crate::reaper::{ReapCall, RecordingReaper};
+
+    fn call(pid: u32, signal: KillSignal, group: bool) -> ReapCall {
+        ReapCall { pid, signal, group }
+    }
+
+    #[tokio::test]
+    async fn test_falls_back_to_tree_when_group_kill_fails() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        table.spawn(101, 100);
+        table.spawn(102, 101);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_stubborn(102),
+        );
+
+        Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .execute(&[100])
+            .await;
+
+        assert_eq!(
+            reaper.calls(),
+            vec![
+                call(100, KillSignal::Term, true),
+                call(100, KillSignal::Term, false),
+                call(101, KillSignal::Term, false),
+                call(102, KillSignal::Term, false),
+                call(100, KillSignal::Kill, true),
+                call(102, KillSignal::Kill, false),
+            ]
+        );
+        assert!(table.processes().iter().all(|p| p.pid == 1));
+    }
+
+    #[test]
+    fn test_plan_sends_nothing() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 1);
+        table.spawn(11, 10);
+        table.spawn(20, 1);
+        let reaper = Arc::new(RecordingReaper::new().with_table(table.clone()));
+
+        let vengeance = Vengeance::new(Duration::from_secs(5))
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_dependency(20, 10)
+            .unwrap()
+            .with_graceful_stop(20, GracefulStop::Command("true".to_string()));
+        let plan: Vec<String> = vengeance
+            .plan(&[1, 10, 20])
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+
+        assert_eq!(
+            plan,
+            vec![
+                "T+0s wave 1: skip PID 1: PID 1 is init",
+                "T+0s wave 1: SIGTERM → process group of PID 10",
+                "T+0s wave 1: SIGTERM → PID 10 (if the group signal fails)",
+                "T+0s wave 1: SIGTERM → PID 11 (child of target 10, if the group signal fails)",
+                "T+5s wave 1: SIGKILL → process group of PID 10",
+                "T+5s wave 1: SIGKILL → PID 10 (if the group signal fails)",
+                "T+5s wave 1: SIGKILL → PID 11 (child of target 10, if the group signal fails)",
+                "T+5s wave 2: ask PID 20 to stop with true",
+                "T+10s wave 2: SIGKILL → process group of PID 20",
+                "T+10s wave 2: SIGKILL → PID 20 (if the group signal fails)",
+            ]
+        );
+        assert!(reaper.calls().is_empty());
+        assert_eq!(table.processes().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_origin_catches_reparented_processes() {
+        let table = Arc::new(MemoryTable::new());
+        table.insert(ProcessInfo::new(100, 1, 0).with_group(100, 100));
+        table.insert(ProcessInfo::new(101, 100, 0).with_group(100, 100));
+        // Double-forked daemon: reparented to init, own group, same session
+        table.insert(ProcessInfo::new(150, 1, 0).with_group(150, 100));
+        table.insert(ProcessInfo::new(200, 1, 0).with_group(200, 200));
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_origin(
+                100,
+                Origin {
+                    sid: Some(100),
+                    pgid: None,
+                },
+            );
+        let mut events = vengeance.events().subscribe();
+
+        vengeance.execute(&[100]).await;
+
+        assert_eq!(
+            reaper.calls(),
+            vec![
+                call(100, KillSignal::Term, true),
+                call(150, KillSignal::Term, false),
+                call(100, KillSignal::Kill, true),
+            ]
+        );
+        assert!(!table.is_alive(150));
+        assert!(table.is_alive(200));
+        let mut how = None;
+        while let Ok(event) = events.try_recv() {
+            if let WickEvent::TreeDiscovered { how: found, .. } = event {
+                how.get_or_insert(found);
+            }
+        }
+        assert_eq!(how, Some(BTreeMap::from([(150, Discovery::Session)])));
+    }
+
+    #[tokio::test]
+    async fn test_group_kill_skips_individual_signals() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        table.spawn(101, 100);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+
+        Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .execute(&[100])
+            .await;
+
+        assert_eq!(
+            reaper.calls(),
+            vec![
+                call(100, KillSignal::Term, true),
+                call(100, KillSignal::Kill, true),
+            ]
+        );
+        assert!(!table.is_alive(101));
+    }
+
+    #[tokio::test]
+    async fn test_custom_ladder() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_stubborn(100),
+        );
+
+        Vengeance::new(Duration::from_secs(60))
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_ladder(vec![
+                Rung::new(KillSignal::Int, Duration::ZERO),
+                Rung::new(KillSignal::Term, Duration::ZERO),
+                Rung::new(KillSignal::Kill, Duration::ZERO),
+            ])
+            .execute(&[100])
+            .await;
+
+        let individual: Vec<KillSignal> = reaper
+            .calls()
+            .iter()
+            .filter(|c| !c.group)
+            .map(|c| c.signal)
+            .collect();
+        assert_eq!(
+            individual,
+            vec![KillSignal::Int, KillSignal::Term, KillSignal::Kill]
+        );
+        assert!(!table.is_alive(100));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_graceful_stop_replaces_first_rung() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        table.spawn(200, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_stubborn(100),
+        );
+        let vengeance = Vengeance::new(Duration::from_millis(200))
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_graceful_stop(100, GracefulStop::Command("true".to_string()))
+            .with_graceful_stop(200, GracefulStop::Command("false".to_string()));
+        let mut rx = vengeance.events().subscribe();
+
+        vengeance.execute(&[100, 200]).await;
+
+        let calls = reaper.calls();
+        // The accepted stop spares 100 its SIGTERM; the failed one falls back to it
+        assert!(!calls
+            .iter()
+            .any(|c| c.pid == 100 && c.signal == KillSignal::Term));
+        assert!(calls
+            .iter()
+            .any(|c| c.pid == 200 && c.signal == KillSignal::Term && !c.group));
+        assert!(calls
+            .iter()
+            .any(|c| c.pid == 100 && c.signal == KillSignal::Kill));
+        assert!(!table.is_alive(100));
+        assert!(!table.is_alive(200));
+
+        let mut stops = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WickEvent::GracefulStopFinished { pid, error } = event {
+                stops.push((pid, error.is_some()));
+            }
+        }
+        stops.sort_unstable();
+        assert_eq!(stops, vec![(100, false), (200, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_targets_run_on_their_own_schedule() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 1);
+        table.spawn(200, 1);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_stubborn(100)
+                .with_stubborn(200),
+        );
+        let shared = default_ladder(Duration::from_millis(300));
+        let vengeance = Vengeance::new(Duration::from_millis(300))
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_target_ladder(
+                200,
+                customize_ladder(&shared, Some(KillSignal::Int), Some(Duration::ZERO)),
+            );
+
+        let started = tokio::time::Instant::now();
+        vengeance.execute(&[100, 200]).await;
+        assert!(started.elapsed() < Duration::from_millis(900));
+
+        let calls = reaper.calls();
+        let first_kill = |pid| {
+            calls
+                .iter()
+                .position(|c| c.pid == pid && c.signal == KillSignal::Kill)
+                .unwrap()
+        };
+        // 200 is force-killed right away while 100 is still in its grace period
+        assert!(first_kill(200) < first_kill(100));
+        assert!(calls
+            .iter()
+            .any(|c| c.pid == 200 && c.signal == KillSignal::Int));
+        assert!(!calls
+            .iter()
+            .any(|c| c.pid == 100 && c.signal == KillSignal::Int));
+    }
+
+    #[test]
+    fn test_shutdown_waves() {
+        // api (1) before db (2) before storage (3); 4 is independent
+        let mut after = HashMap::new();
+        after.insert(2, BTreeSet::from([1]));
+        after.insert(3, BTreeSet::from([2, 99]));
+        assert_eq!(
+            shutdown_waves(&[3, 2, 1, 4], &after),
+            Ok(vec![vec![1, 4], vec![2], vec![3]])
+        );
+
+        after.insert(1, BTreeSet::from([3]));
+        assert_eq!(
+            shutdown_waves(&[1, 2, 3, 4], &after),
+            Err("Dependency cycle between targets: [1, 2, 3]".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_waves_wait_for_previous_wave() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 0);
+        table.spawn(20, 0);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_dependency(20, 10)
+            .unwrap();
+        let mut rx = vengeance.events().subscribe();
+
+        vengeance.execute(&[10, 20]).await;
+
+        let order: Vec<u32> = reaper
+            .calls()
+            .iter()
+            .filter(|c| c.signal == KillSignal::Term)
+            .map(|c| c.pid)
+            .collect();
+        assert_eq!(order, vec![10, 20]);
+
+        let mut waves = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WickEvent::WaveStarted { wave, pids } = event {
+                waves.push((wave, pids));
+            }
+        }
+        assert_eq!(waves, vec![(1, vec![10]), (2, vec![20])]);
+    }
+
+    #[tokio::test]
+    async fn test_next_wave_waits_for_the_whole_tree() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 0);
+        table.spawn(11, 10);
+        table.spawn(20, 0);
+        // 11 shrugs off the only rung of the ladder
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery()
+                .with_stubborn(11),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_ladder(vec![Rung::new(KillSignal::Term, Duration::ZERO)])
+            .with_dependency(20, 10)
+            .unwrap();
+
+        vengeance.execute(&[10, 20]).await;
+
+        let calls = reaper.calls();
+        let killed = calls
+            .iter()
+            .position(|c| *c == call(11, KillSignal::Kill, false))
+            .expect("the survivor of wave 1 is force-killed");
+        let second_wave = calls
+            .iter()
+            .position(|c| c.pid == 20)
+            .expect("wave 2 is signalled");
+        assert!(killed < second_wave);
+        assert!(!table.is_alive(11));
+    }
+
+    #[tokio::test]
+    async fn test_unkillable_survivors_do_not_hold_the_next_wave_forever() {
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(10, 0);
+        table.spawn(11, 10);
+        table.spawn(20, 0);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery()
+                .with_unkillable(11),
+        );
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_dependency(20, 10)
+            .unwrap()
+            .with_wave_confirmation(Duration::from_millis(100), 2);
+        let mut rx = vengeance.events().subscribe();
+
+        vengeance.execute(&[10, 20]).await;
+
+        let calls = reaper.calls();
+        let retries = calls
+            .iter()
+            .filter(|c| **c == call(11, KillSignal::Kill, false))
+            .count();
+        assert_eq!(retries, 2);
+        assert!(calls.iter().any(|c| c.pid == 20));
+        assert!(table.is_alive(11));
+        assert!(!table.is_alive(20));
+
+        let mut expired = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WickEvent::GraceExpired { survivors } = event {
+                expired.push(survivors);
+            }
+        }
+        assert_eq!(expired.last(), Some(&vec![11]));
+    }
+
+    #[test]
+    fn test_dependency_cycles_are_refused() {
+        let result = Vengeance::new(Duration::ZERO)
+            .with_dependency(1, 2)
+            .and_then(|vengeance| vengeance.with_dependency(2, 1));
+        assert_eq!(
+            result.err(),
+            Some("Dependency cycle between targets: [1, 2]".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_protected_group_members_are_not_group_signalled() {
+        use crate::process_table::ProcessInfo;
+        use crate::safety::parse_protect_rules;
+
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 50);
+        table.insert(ProcessInfo::new(101, 100, 0).with_name("sshd"));
+        table.spawn(102, 100);
+        let reaper = Arc::new(
+            RecordingReaper::new()
+                .with_table(table.clone())
+                .with_group_delivery(),
+        );
+
+        Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_safety(Safety::new(parse_protect_rules("name:sshd").unwrap()))
+            .execute(&[100])
+            .await;
+
+        // The group signal would reach sshd, so the tree is signalled PID by PID
+        let calls = reaper.calls();
+        assert!(!calls.iter().any(|c| c.group));
+        assert!(!calls.iter().any(|c| c.pid == 101));
+        assert!(calls.contains(&call(102, KillSignal::Term, false)));
+        assert!(table.is_alive(101));
+        assert!(!table.is_alive(100));
+        assert!(!table.is_alive(102));
+    }
+
+    #[tokio::test]
+    async fn test_protected_processes_are_spared() {
+        use crate::process_table::ProcessInfo;
+        use crate::safety::parse_protect_rules;
+
+        let table = Arc::new(MemoryTable::new());
+        table.spawn(100, 50);
+        table.insert(ProcessInfo::new(101, 100, 0).with_name("sshd"));
+        table.spawn(102, 100);
+        table.spawn(200, 50);
+        let reaper = Arc::new(RecordingReaper::new().with_table(table.clone()));
+        let vengeance = Vengeance::new(Duration::ZERO)
+            .with_table(table.clone())
+            .with_reaper(reaper.clone())
+            .with_safety(Safety::new(
+                parse_protect_rules("name:sshd,pid:200").unwrap(),
+            ));
+        let mut rx = vengeance.events().subscribe();
+
+        vengeance.execute(&[100, 200]).await;
+
+        assert!(table.is_alive(101));
+        assert!(table.is_alive(200));
+        assert!(!table.is_alive(100));
+        assert!(!table.is_alive(102));
+        assert!(!reaper.calls().iter().any(|c| c.pid == 101 || c.pid == 200));
+
+        let mut refused = Vec::new();
+        while let Ok(event) = rx.try_recv() {
+            if let WickEvent::TargetRefused { pid, .. } = event {
+                refused.push(pid);
+            }
+        }
+        assert_eq!(refused, vec![200]);
+    }
+}
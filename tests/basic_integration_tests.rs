@@ -3,7 +3,7 @@ use std::process::Command;
 /// Helper function to build the process-wick binary
 fn build_binary() -> String {
     let output = Command::new("cargo")
-        .args(&["build", "--release"])
+        .args(["build", "--release"])
         .output()
         .expect("Failed to build binary");
 
@@ -58,7 +58,7 @@ fn test_missing_required_args() {
 
     // Try to run without required --targets argument
     let output = Command::new(&binary_path)
-        .args(&["--dog", "1234", "--tick", "1"])
+        .args(["--dog", "1234", "--tick", "1"])
         .output()
         .expect("Failed to execute process-wick");
 
@@ -84,7 +84,7 @@ fn test_log_level_validation() {
 
     for level in &valid_levels {
         let output = Command::new(&binary_path)
-            .args(&["--dog", "1234", "--targets", "5678", "--log-level", level])
+            .args(["--dog", "1234", "--targets", "5678", "--log-level", level])
             .output()
             .expect("Failed to execute process-wick");
 
@@ -104,7 +104,7 @@ fn test_argument_parsing() {
 
     // Test that all arguments are properly parsed
     let output = Command::new(&binary_path)
-        .args(&[
+        .args([
             "--dog",
             "1234",
             "--targets",
@@ -133,7 +133,7 @@ fn test_multiple_targets_parsing() {
 
     // Test with multiple target PIDs (comma-separated)
     let output = Command::new(&binary_path)
-        .args(&["--dog", "1234", "--targets", "5678,9012,3456,7890"])
+        .args(["--dog", "1234", "--targets", "5678,9012,3456,7890"])
         .output()
         .expect("Failed to execute process-wick");
 
@@ -150,7 +150,7 @@ fn test_invalid_targets_argument() {
 
     // Test with an invalid PID in the targets list
     let output = Command::new(&binary_path)
-        .args(&["--dog", "1234", "--targets", "5678,notanumber,9012"])
+        .args(["--dog", "1234", "--targets", "5678,notanumber,9012"])
         .output()
         .expect("Failed to execute process-wick");
 
@@ -170,7 +170,7 @@ fn test_default_values() {
     // Test with minimal arguments (should use defaults)
     // Use a non-existent PID to avoid hanging
     let output = Command::new(&binary_path)
-        .args(&[
+        .args([
             "--dog",
             "999999", // Non-existent PID
             "--targets",
@@ -190,3 +190,180 @@ fn test_default_values() {
         "Should work with default values"
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn test_daemon_subcommand_parsing() {
+    let binary_path = build_binary();
+
+    let output = Command::new(&binary_path)
+        .args(["daemon", "--help"])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(output.status.success(), "Daemon help should display");
+    let help_text = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        help_text.contains("--socket"),
+        "Daemon help should contain --socket option"
+    );
+
+    // Watch-mode flags don't belong to the daemon
+    let output = Command::new(&binary_path)
+        .args([
+            "daemon",
+            "--socket",
+            "/tmp/unused.sock",
+            "--targets",
+            "1234",
+        ])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(!output.status.success(), "Daemon should reject --targets");
+}
+
+#[test]
+fn test_check_config() {
+    let binary_path = build_binary();
+    let dir = std::env::temp_dir();
+
+    let valid = dir.join("process-wick-check-valid.toml");
+    std::fs::write(
+        &valid,
+        "[[contract]]\nid = \"web\"\ndog = 1234\ntargets = [5678, { name = \"worker\" }]\n",
+    )
+    .unwrap();
+    let output = Command::new(&binary_path)
+        .args(["check-config", valid.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(output.status.success(), "Valid config should pass");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("web: dog pid 1234"),
+        "Should list the contract"
+    );
+
+    let invalid = dir.join("process-wick-check-invalid.toml");
+    std::fs::write(&invalid, "[[contract]]\ndog = 1234\ntargets = []\n").unwrap();
+    let output = Command::new(&binary_path)
+        .args(["check-config", invalid.to_str().unwrap()])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(
+        !output.status.success(),
+        "Config without targets should fail"
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("has no targets"),
+        "Error should explain what is wrong"
+    );
+
+    let _ = std::fs::remove_file(valid);
+    let _ = std::fs::remove_file(invalid);
+}
+
+#[test]
+#[cfg(unix)]
+fn test_dry_run_now() {
+    let binary_path = build_binary();
+    let mut target = Command::new("sleep")
+        .arg("30")
+        .spawn()
+        .expect("Failed to spawn target");
+    let dog = std::process::id().to_string();
+    let targets = target.id().to_string();
+
+    let output = Command::new(&binary_path)
+        .args(["--dog", &dog, "--targets", &targets, "--dry-run", "--now"])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(output.status.success(), "Dry run should succeed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains(&format!("SIGKILL → PID {}", targets)),
+        "Plan should list the force kill"
+    );
+    assert!(
+        target.try_wait().unwrap().is_none(),
+        "Dry run must not signal the target"
+    );
+
+    let _ = target.kill();
+    let _ = target.wait();
+}
+
+#[test]
+#[cfg(unix)]
+fn test_kill_subcommand() {
+    let binary_path = build_binary();
+    let mut target = Command::new("sleep")
+        .arg("30")
+        .spawn()
+        .expect("Failed to spawn target");
+
+    let output = Command::new(&binary_path)
+        .args([
+            "kill",
+            "--targets",
+            &target.id().to_string(),
+            "--vengeance-delay",
+            "1",
+        ])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(
+        output.status.success(),
+        "Kill should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(target.wait().is_ok(), "Target should be dead");
+
+    let output = Command::new(&binary_path)
+        .args(["kill", "--targets", "1"])
+        .output()
+        .expect("Failed to execute process-wick");
+    assert!(!output.status.success(), "Killing init must be refused");
+}
+
+#[test]
+#[cfg(unix)]
+fn test_on_self_signal_execute() {
+    let binary_path = build_binary();
+    let mut target = Command::new("sleep")
+        .arg("30")
+        .spawn()
+        .expect("Failed to spawn target");
+    let dog = std::process::id().to_string();
+    let targets = target.id().to_string();
+
+    let mut wick = Command::new(&binary_path)
+        .args([
+            "--dog",
+            &dog,
+            "--targets",
+            &targets,
+            "--vengeance-delay",
+            "1",
+            "--on-self-signal",
+            "execute",
+        ])
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .expect("Failed to execute process-wick");
+    std::thread::sleep(std::time::Duration::from_secs(1));
+    let status = Command::new("kill")
+        .args(["-TERM", &wick.id().to_string()])
+        .status()
+        .expect("Failed to signal process-wick");
+    assert!(status.success());
+
+    assert!(
+        wick.wait().unwrap().success(),
+        "process-wick should exit cleanly"
+    );
+    assert!(
+        target.try_wait().unwrap().is_some(),
+        "SIGTERM should have executed vengeance on the target"
+    );
+}
@@ -1,3 +1,5 @@
+#![allow(clippy::zombie_processes)]
+
 use std::fs;
 use std::path::Path;
 use std::process::{Child, Command, Stdio};
@@ -8,7 +10,7 @@ use std::time::Duration;
 /// Helper function to build the process-wick binary
 fn build_binary() -> String {
     let output = Command::new("cargo")
-        .args(&["build", "--release"])
+        .args(["build", "--release"])
         .output()
         .expect("Failed to build binary");
 
@@ -95,7 +97,7 @@ fn test_basic_functionality() {
 
     let handle = thread::spawn(move || {
         let output = Command::new(&binary_path_clone)
-            .args(&[
+            .args([
                 "--dog",
                 &dog_pid.to_string(),
                 "--targets",
@@ -125,11 +127,11 @@ fn test_basic_functionality() {
 
     // Verify that target processes were killed
     assert!(
-        !target1.try_wait().unwrap().is_some(),
+        target1.try_wait().unwrap().is_none(),
         "Target1 should have been killed"
     );
     assert!(
-        !target2.try_wait().unwrap().is_some(),
+        target2.try_wait().unwrap().is_none(),
         "Target2 should have been killed"
     );
 
@@ -140,6 +142,7 @@ fn test_basic_functionality() {
 }
 
 #[test]
+#[allow(unreachable_code)]
 fn test_default_dog_pid() {
     let binary_path = build_binary();
 
@@ -153,7 +156,7 @@ fn test_default_dog_pid() {
 
     let _handle = thread::spawn(move || {
         let output = Command::new(&binary_path_clone)
-            .args(&[
+            .args([
                 "--targets",
                 &target1_pid.to_string(),
                 "--tick",
@@ -204,7 +207,7 @@ fn test_logging_functionality() {
 
     let handle = thread::spawn(move || {
         let output = Command::new(&binary_path_clone)
-            .args(&[
+            .args([
                 "--dog",
                 &dog_pid.to_string(),
                 "--targets",
@@ -324,7 +327,7 @@ fn test_multiple_targets() {
     // Verify all target processes were killed
     for target in &mut targets {
         assert!(
-            !target.try_wait().unwrap().is_some(),
+            target.try_wait().unwrap().is_none(),
             "All targets should have been killed"
         );
     }
@@ -356,7 +359,7 @@ fn test_vengeance_delay() {
 
     let handle = thread::spawn(move || {
         let output = Command::new(&binary_path_clone)
-            .args(&[
+            .args([
                 "--dog",
                 &dog_pid.to_string(),
                 "--targets",
@@ -402,7 +405,7 @@ fn test_invalid_pid_handling() {
 
     // Try to run process-wick with an invalid dog PID
     let output = Command::new(&binary_path)
-        .args(&[
+        .args([
             "--dog",
             "999999",
             "--targets",
@@ -463,7 +466,7 @@ fn test_missing_required_args() {
 
     // Try to run without required --targets argument
     let output = Command::new(&binary_path)
-        .args(&["--dog", "1234", "--tick", "1"])
+        .args(["--dog", "1234", "--tick", "1"])
         .output()
         .expect("Failed to execute process-wick");
 
@@ -501,7 +504,7 @@ fn test_process_tree_killing() {
 
     let handle = thread::spawn(move || {
         let output = Command::new(&binary_path_clone)
-            .args(&[
+            .args([
                 "--dog",
                 &parent_pid.to_string(),
                 "--targets",
@@ -532,7 +535,7 @@ fn test_process_tree_killing() {
 
     // Verify target process was killed
     assert!(
-        !target.try_wait().unwrap().is_some(),
+        target.try_wait().unwrap().is_none(),
         "Target should have been killed"
     );
 